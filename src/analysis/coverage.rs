@@ -0,0 +1,327 @@
+use halo2_proofs::{
+    arithmetic::Field,
+    plonk::{Any, Circuit, Column, ConstraintSystem, ErrorFront, Expression, Selector},
+};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+};
+
+use crate::layout::{AnyCell, Layout, NamedCell};
+
+/// How an advice cell is tied to the rest of the circuit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    /// Queried by a gate on a row where the gate is enabled.
+    Gated,
+    /// Queried by a lookup input on a row where the lookup is enabled.
+    LookedUp,
+    /// Only copy-constrained: its value is whatever the other cells of its class hold.
+    CopyConstrained,
+    /// Nothing looks at it.
+    Free,
+}
+
+#[derive(Debug, Clone)]
+pub struct CellCoverage {
+    pub cell: NamedCell,
+    pub status: Status,
+    /// No gate or lookup checks this cell nor the cells it is copied to,
+    /// yet the value ends up in an instance column.
+    pub unchecked_public: bool,
+}
+
+/// Which advice cells are touched by gates, lookups and copy constraints,
+/// computed from the constraint system and the layout only, no witness needed.
+#[derive(Debug, Clone)]
+pub struct CoverageReport {
+    pub cells: Vec<CellCoverage>,
+}
+
+/// Selectors and advice queries (column, rotation) of a term of a gate or lookup expression:
+/// the queries are checked on the rows where all the selectors are on.
+type Queries = (Vec<Selector>, Vec<(usize, i32)>);
+
+/// The terms of `expression`. A sum keeps the terms of both sides, `q1 * a + q2 * b`
+/// checks `a` where `q1` is on and `b` where `q2` is on; a product pairs them up.
+pub(super) fn queries<F: Field>(expression: &Expression<F>) -> Vec<Queries> {
+    let none = || vec![(vec![], vec![])];
+    let sum = |mut left: Vec<Queries>, right: Vec<Queries>| {
+        for term in right {
+            if !left.contains(&term) {
+                left.push(term);
+            }
+        }
+        left
+    };
+    let product = |left: Vec<Queries>, right: Vec<Queries>| {
+        let mut terms: Vec<Queries> = vec![];
+        for (s1, a1) in left.iter() {
+            for (s2, a2) in right.iter() {
+                let mut selectors = s1.clone();
+                selectors.extend(s2.iter().filter(|s| !s1.contains(s)));
+                let mut advice = a1.clone();
+                advice.extend(a2.iter().filter(|a| !a1.contains(a)));
+                let term = (selectors, advice);
+                if !terms.contains(&term) {
+                    terms.push(term);
+                }
+            }
+        }
+        terms
+    };
+    expression.evaluate(
+        &|_| none(),
+        &|selector| vec![(vec![selector], vec![])],
+        &|_| none(),
+        &|query| vec![(vec![], vec![(query.column_index(), query.rotation().0)])],
+        &|_| none(),
+        &|_| none(),
+        &|q| q,
+        &sum,
+        &product,
+        &|q, _| q,
+    )
+}
+
+impl CoverageReport {
+    pub fn build<F: Field, C: Circuit<F>>(circuit: &C) -> Result<Self, ErrorFront> {
+        let (cs, layout) = Layout::configure_and_synthesize(circuit)?;
+        Ok(Self::from_parts(&cs, &layout))
+    }
+
+    pub fn from_parts<F: Field>(cs: &ConstraintSystem<F>, layout: &Layout<F>) -> Self {
+        let mut enabled: HashMap<Selector, HashSet<usize>> = HashMap::new();
+        for (selector, row) in layout.selectors.iter() {
+            enabled.entry(*selector).or_default().insert(*row);
+        }
+        let assigned: HashSet<(usize, usize)> = layout
+            .advice
+            .iter()
+            .map(|c| (c.column.index(), c.row))
+            .collect();
+
+        // Marks the cells queried by each term of `expression` on every row where all the
+        // selectors of the term are on. A term without selector applies to every row.
+        let touched = |expressions: Vec<&Expression<F>>| {
+            let mut cells = HashSet::new();
+            for (selectors, advice) in expressions.into_iter().flat_map(queries) {
+                let rows: Vec<usize> = match selectors.split_first() {
+                    None => assigned.iter().map(|(_, row)| *row).collect(),
+                    Some((first, rest)) => enabled
+                        .get(first)
+                        .into_iter()
+                        .flatten()
+                        .filter(|row| {
+                            rest.iter()
+                                .all(|s| enabled.get(s).is_some_and(|r| r.contains(row)))
+                        })
+                        .copied()
+                        .collect(),
+                };
+                for row in rows {
+                    for (column, rotation) in advice.iter() {
+                        let queried = row as i64 + *rotation as i64;
+                        if queried >= 0 {
+                            cells.insert((*column, queried as usize));
+                        }
+                    }
+                }
+            }
+            cells
+        };
+
+        let gated = touched(
+            cs.gates()
+                .iter()
+                .flat_map(|gate| gate.polynomials().iter())
+                .collect(),
+        );
+        let looked_up = touched(
+            cs.lookups()
+                .iter()
+                .flat_map(|lookup| lookup.input_expressions().iter())
+                .collect(),
+        );
+
+        let classes = layout.copy_classes();
+        let copied: HashSet<AnyCell> = layout.copies.iter().flat_map(|(l, r)| [*l, *r]).collect();
+
+        let status = |cell: &NamedCell| {
+            let key = (cell.column.index(), cell.row);
+            if gated.contains(&key) {
+                Status::Gated
+            } else if looked_up.contains(&key) {
+                Status::LookedUp
+            } else if copied.contains(&(Column::<Any>::from(cell.column), cell.row)) {
+                Status::CopyConstrained
+            } else {
+                Status::Free
+            }
+        };
+
+        // copy classes where some cell is checked by a gate or a lookup
+        let checked: HashSet<AnyCell> = layout
+            .advice
+            .iter()
+            .filter(|c| matches!(status(c), Status::Gated | Status::LookedUp))
+            .map(|c| classes.advice_root(c))
+            .collect();
+        // copy classes ending in an instance column
+        let public: HashSet<AnyCell> = copied
+            .iter()
+            .filter(|(column, _)| *column.column_type() == Any::Instance)
+            .map(|c| classes.root(*c))
+            .collect();
+
+        let cells = layout
+            .advice
+            .iter()
+            .map(|cell| {
+                let root = classes.advice_root(cell);
+                CellCoverage {
+                    cell: cell.clone(),
+                    status: status(cell),
+                    unchecked_public: public.contains(&root) && !checked.contains(&root),
+                }
+            })
+            .collect();
+
+        Self { cells }
+    }
+
+    pub fn unchecked_public(&self) -> impl Iterator<Item = &CellCoverage> {
+        self.cells.iter().filter(|c| c.unchecked_public)
+    }
+}
+
+impl fmt::Display for CoverageReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for c in self.cells.iter() {
+            write!(
+                f,
+                "{:<16} {}/{} (column {}, row {})",
+                format!("{:?}", c.status),
+                c.cell.region,
+                c.cell.annotation,
+                c.cell.column.index(),
+                c.cell.row
+            )?;
+            if c.unchecked_public {
+                write!(f, "  <- unchecked value exposed as public input")?;
+            }
+            writeln!(f)?;
+        }
+        write!(
+            f,
+            "{} advice cells, {} unchecked public",
+            self.cells.len(),
+            self.unchecked_public().count()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner, Value},
+        halo2curves::bn256::Fr,
+        plonk::Advice,
+        poly::Rotation,
+    };
+
+    use super::*;
+    use crate::mul::{mul1, mul2};
+
+    /// `q1 * a + q2 * b`, with `q1` on row 0 and `q2` on row 1.
+    #[derive(Default)]
+    struct EitherCircuit;
+
+    impl Circuit<Fr> for EitherCircuit {
+        type Config = (Column<Advice>, Column<Advice>, Selector, Selector);
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            let (a, b) = (meta.advice_column(), meta.advice_column());
+            let (q1, q2) = (meta.selector(), meta.selector());
+            meta.create_gate("either", |meta| {
+                let a = meta.query_advice(a, Rotation::cur());
+                let b = meta.query_advice(b, Rotation::cur());
+                vec![meta.query_selector(q1) * a + meta.query_selector(q2) * b]
+            });
+            (a, b, q1, q2)
+        }
+
+        fn synthesize(
+            &self,
+            (a, b, q1, q2): Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), ErrorFront> {
+            layouter.assign_region(
+                || "main region",
+                |mut region| {
+                    q1.enable(&mut region, 0)?;
+                    q2.enable(&mut region, 1)?;
+                    for row in 0..2 {
+                        let zero = || Value::known(Fr::ZERO);
+                        region.assign_advice(|| format!("a{}", row), a, row, zero)?;
+                        region.assign_advice(|| format!("b{}", row), b, row, zero)?;
+                    }
+                    Ok(())
+                },
+            )
+        }
+    }
+
+    fn find<'a>(report: &'a CoverageReport, annotation: &str) -> &'a CellCoverage {
+        report
+            .cells
+            .iter()
+            .find(|c| c.cell.region == "main region" && c.cell.annotation == annotation)
+            .unwrap()
+    }
+
+    #[test]
+    fn mul1_out_is_unchecked_public() {
+        let circuit = mul1::MultiplicationCircuit {
+            a: Value::known(Fr::from(3)),
+            b: Value::known(Fr::from(4)),
+        };
+        let report = CoverageReport::build(&circuit).unwrap();
+
+        let out = find(&report, "out");
+        assert_eq!(out.status, Status::CopyConstrained);
+        assert!(out.unchecked_public);
+        assert_eq!(find(&report, "a").status, Status::Free);
+        assert_eq!(find(&report, "b").status, Status::Free);
+        assert_eq!(report.unchecked_public().count(), 1);
+    }
+
+    #[test]
+    fn mul2_cells_are_gated() {
+        let circuit = mul2::MultiplicationCircuit {
+            a: Value::known(Fr::from(3)),
+            b: Value::known(Fr::from(4)),
+        };
+        let report = CoverageReport::build(&circuit).unwrap();
+
+        for annotation in ["a", "b", "out"] {
+            assert_eq!(find(&report, annotation).status, Status::Gated);
+        }
+        assert_eq!(report.unchecked_public().count(), 0);
+    }
+
+    #[test]
+    fn sum_of_terms_checks_each_under_its_own_selector() {
+        let report = CoverageReport::build(&EitherCircuit).unwrap();
+
+        assert_eq!(find(&report, "a0").status, Status::Gated);
+        assert_eq!(find(&report, "b1").status, Status::Gated);
+        assert_eq!(find(&report, "b0").status, Status::Free);
+        assert_eq!(find(&report, "a1").status, Status::Free);
+    }
+}
@@ -0,0 +1,5 @@
+mod coverage;
+mod underconstrained;
+
+pub use coverage::{CellCoverage, CoverageReport, Status};
+pub use underconstrained::{find_underconstrained, AnalysisError, Finding};
@@ -0,0 +1,520 @@
+use halo2_proofs::{
+    arithmetic::Field,
+    dev::{CellValue, MockProver, VerifyFailure},
+    halo2curves::ff::FromUniformBytes,
+    plonk::{Circuit, ErrorFront, Expression, Selector},
+};
+use rand::{rngs::StdRng, SeedableRng};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+};
+
+use super::coverage::queries;
+use crate::layout::{AnyCell, Layout, NamedCell};
+
+/// Groups a forgery may change, the first one included.
+const MAX_GROUPS: usize = 5;
+/// Groups tried while repairing the gates broken by one forged group.
+const REPAIR_BUDGET: usize = 128;
+
+/// A group of advice cells we could change without the verifier noticing.
+#[derive(Debug, Clone)]
+pub struct Finding {
+    /// The first cell of the group, in assignment order.
+    pub cell: NamedCell,
+    /// The other advice cells copy-constrained to `cell`, they were changed together.
+    pub copies: Vec<NamedCell>,
+    pub honest: String,
+    pub forged: String,
+    /// The groups changed after `cell` to satisfy the gates it broke, in that order.
+    /// Empty when `cell` can change on its own.
+    pub repairs: Vec<Finding>,
+}
+
+impl Finding {
+    /// `cell`, its copies and the cells of the repairs.
+    pub fn cells(&self) -> Vec<&NamedCell> {
+        std::iter::once(&self.cell)
+            .chain(self.copies.iter())
+            .chain(self.repairs.iter().flat_map(|r| r.cells()))
+            .collect()
+    }
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}/{} (column {}, row {}): {} can be replaced by {}",
+            self.cell.region,
+            self.cell.annotation,
+            self.cell.column.index(),
+            self.cell.row,
+            self.honest,
+            self.forged
+        )?;
+        for copy in self.copies.iter() {
+            write!(
+                f,
+                "\n    together with {}/{} (column {}, row {})",
+                copy.region,
+                copy.annotation,
+                copy.column.index(),
+                copy.row
+            )?;
+        }
+        for repair in self.repairs.iter() {
+            write!(f, "\n  and {}", repair.to_string().replace('\n', "\n  "))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub enum AnalysisError {
+    Synthesis(ErrorFront),
+    /// We need a witness that verifies to start from.
+    HonestWitnessRejected(Vec<VerifyFailure>),
+}
+
+impl fmt::Display for AnalysisError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AnalysisError::Synthesis(err) => write!(f, "synthesis failed: {}", err),
+            AnalysisError::HonestWitnessRejected(failures) => {
+                write!(f, "the honest witness doesn't verify")?;
+                for failure in failures.iter() {
+                    write!(f, "\n    {}", failure)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for AnalysisError {}
+
+impl From<ErrorFront> for AnalysisError {
+    fn from(err: ErrorFront) -> Self {
+        AnalysisError::Synthesis(err)
+    }
+}
+
+/// Tries to change every assigned advice cell of an honest witness and reports
+/// the ones for which `MockProver::verify` still succeeds.
+///
+/// Cells linked by copy constraints are changed together, otherwise the permutation
+/// argument would catch every single-cell change of a copied value.
+/// The candidates are a few edge values, the public inputs, and a random element.
+///
+/// When a group can't change on its own, we try `honest + 1` and repair the gates it breaks:
+/// we take the broken constraint querying the fewest other groups, solve it for one of them
+/// if it is linear in that group, and go on with the constraints this one breaks,
+/// backtracking on dead ends. A forgery is found when no gate is broken and
+/// `MockProver::verify` succeeds, with at most `MAX_GROUPS` groups changed.
+/// That is how the leaf hash of `MerkleCircuitNoHash3` is forged: the swap and hash
+/// gates are repaired with the neighbor node. Lookups and the permutation are only
+/// checked at the end, they never guide the search.
+pub fn find_underconstrained<F: FromUniformBytes<64> + Ord, C: Circuit<F>>(
+    k: u32,
+    circuit: &C,
+    instances: Vec<Vec<F>>,
+) -> Result<Vec<Finding>, AnalysisError> {
+    let mut prover = MockProver::run(k, circuit, instances.clone())?;
+    prover
+        .verify()
+        .map_err(AnalysisError::HonestWitnessRejected)?;
+
+    let (cs, layout) = Layout::configure_and_synthesize(circuit)?;
+    let groups = copy_groups(&layout);
+    let search = Search::new(
+        &groups,
+        cs.gates()
+            .iter()
+            .flat_map(|gate| gate.polynomials().iter())
+            .collect(),
+        &layout,
+        &instances,
+        1 << k,
+        &prover,
+    );
+    let mut rng = StdRng::seed_from_u64(0);
+    let mut findings = vec![];
+
+    // the first group changed, with the groups repairing it
+    let finding = |changed: &[usize], values: &[(F, F)]| {
+        let mut changes = changed
+            .iter()
+            .zip(values)
+            .map(|(&g, (honest, forged))| Finding {
+                cell: groups[g][0].clone(),
+                copies: groups[g][1..].to_vec(),
+                honest: format!("{:?}", honest),
+                forged: format!("{:?}", forged),
+                repairs: vec![],
+            });
+        let mut first = changes.next().unwrap();
+        first.repairs = changes.collect();
+        first
+    };
+
+    for (index, group) in groups.iter().enumerate() {
+        let honest = match value(&prover, group) {
+            Some(v) => v,
+            None => continue,
+        };
+
+        let mut candidates = vec![
+            honest + F::ONE,
+            honest - F::ONE,
+            -honest,
+            F::ZERO,
+            F::ONE,
+            F::random(&mut rng),
+        ];
+        candidates.extend(instances.iter().flatten().copied());
+
+        let forged = candidates.into_iter().filter(|v| *v != honest).find(|v| {
+            set(&mut prover, group, *v);
+            prover.verify().is_ok()
+        });
+
+        match forged {
+            Some(forged) => {
+                set(&mut prover, group, honest);
+                findings.push(finding(&[index], &[(honest, forged)]));
+            }
+            None => {
+                set(&mut prover, group, honest + F::ONE);
+                let mut changed = vec![index];
+                let mut budget = REPAIR_BUDGET;
+                let repaired = search.repair(&mut prover, &mut changed, &mut budget);
+                // the values before restoring the honest witness
+                let values = changed
+                    .iter()
+                    .map(|&g| {
+                        let forged = value(&prover, &groups[g]).unwrap_or(F::ZERO);
+                        (search.honest[g].unwrap_or(F::ZERO), forged)
+                    })
+                    .collect::<Vec<_>>();
+                for &g in changed.iter() {
+                    set(&mut prover, &groups[g], search.honest[g].unwrap_or(F::ZERO));
+                }
+                if repaired {
+                    findings.push(finding(&changed, &values));
+                }
+            }
+        }
+    }
+
+    Ok(findings)
+}
+
+/// What the repair needs to evaluate the gates on any row of the witness.
+struct Search<'a, F: Field> {
+    groups: &'a [Vec<NamedCell>],
+    /// the group of every assigned advice cell, by (column, row)
+    group_of: HashMap<(usize, usize), usize>,
+    /// the value of every group in the honest witness
+    honest: Vec<Option<F>>,
+    /// every gate polynomial, and the advice cells it queries as (column, rotation)
+    polys: Vec<(&'a Expression<F>, Vec<(usize, i32)>)>,
+    enabled: HashSet<(Selector, usize)>,
+    fixed: &'a HashMap<(usize, usize), F>,
+    instances: &'a [Vec<F>],
+    n: usize,
+}
+
+impl<'a, F: FromUniformBytes<64> + Ord> Search<'a, F> {
+    fn new(
+        groups: &'a [Vec<NamedCell>],
+        polys: Vec<&'a Expression<F>>,
+        layout: &'a Layout<F>,
+        instances: &'a [Vec<F>],
+        n: usize,
+        prover: &MockProver<F>,
+    ) -> Self {
+        let group_of = groups
+            .iter()
+            .enumerate()
+            .flat_map(|(g, group)| group.iter().map(move |c| ((c.column.index(), c.row), g)))
+            .collect();
+        Self {
+            groups,
+            group_of,
+            honest: groups.iter().map(|group| value(prover, group)).collect(),
+            polys: polys
+                .into_iter()
+                .map(|poly| {
+                    let mut advice: Vec<_> = queries(poly)
+                        .into_iter()
+                        .flat_map(|(_, advice)| advice)
+                        .collect();
+                    advice.sort_unstable();
+                    advice.dedup();
+                    (poly, advice)
+                })
+                .collect(),
+            enabled: layout.selectors.iter().copied().collect(),
+            fixed: &layout.fixed,
+            instances,
+            n,
+        }
+    }
+
+    fn row(&self, row: usize, rotation: i32) -> usize {
+        (row as i64 + rotation as i64).rem_euclid(self.n as i64) as usize
+    }
+
+    fn evaluate(&self, prover: &MockProver<F>, poly: &Expression<F>, row: usize) -> F {
+        let advice = prover.advice();
+        poly.evaluate(
+            &|c| c,
+            &|selector| {
+                if self.enabled.contains(&(selector, row)) {
+                    F::ONE
+                } else {
+                    F::ZERO
+                }
+            },
+            &|query| {
+                let at = self.row(row, query.rotation().0);
+                self.fixed
+                    .get(&(query.column_index(), at))
+                    .copied()
+                    .unwrap_or(F::ZERO)
+            },
+            &|query| match advice[query.column_index()][self.row(row, query.rotation().0)] {
+                CellValue::Assigned(v) => v,
+                _ => F::ZERO,
+            },
+            &|query| {
+                self.instances
+                    .get(query.column_index())
+                    .and_then(|column| column.get(self.row(row, query.rotation().0)))
+                    .copied()
+                    .unwrap_or(F::ZERO)
+            },
+            &|_| F::ZERO,
+            &|a| -a,
+            &|a, b| a + b,
+            &|a, b| a * b,
+            &|a, scalar| a * scalar,
+        )
+    }
+
+    /// The constraints, as (polynomial, row), broken on a row querying a `changed` group.
+    fn broken(&self, prover: &MockProver<F>, changed: &[usize]) -> Vec<(usize, usize)> {
+        let mut broken = vec![];
+        for &g in changed.iter() {
+            for cell in self.groups[g].iter() {
+                for (p, (poly, advice)) in self.polys.iter().enumerate() {
+                    for (column, rotation) in advice.iter() {
+                        if *column != cell.column.index() {
+                            continue;
+                        }
+                        let row = self.row(cell.row, -rotation);
+                        if !broken.contains(&(p, row))
+                            && self.evaluate(prover, poly, row) != F::ZERO
+                        {
+                            broken.push((p, row));
+                        }
+                    }
+                }
+            }
+        }
+        broken
+    }
+
+    /// The groups queried by a constraint, other than the `changed` ones.
+    fn unchanged(&self, (p, row): (usize, usize), changed: &[usize]) -> Vec<usize> {
+        let mut groups: Vec<usize> = vec![];
+        for (column, rotation) in self.polys[p].1.iter() {
+            if let Some(&g) = self.group_of.get(&(*column, self.row(row, *rotation))) {
+                if !changed.contains(&g) && !groups.contains(&g) {
+                    groups.push(g);
+                }
+            }
+        }
+        groups
+    }
+
+    /// The value of `group` satisfying the constraint, if it is linear in it.
+    fn solve(&self, prover: &mut MockProver<F>, (p, row): (usize, usize), g: usize) -> Option<F> {
+        let group = &self.groups[g];
+        let current = value(prover, group)?;
+        let poly = self.polys[p].0;
+        let mut at = |x: F| {
+            set(prover, group, x);
+            self.evaluate(prover, poly, row)
+        };
+        let (e0, e1, e2) = (at(F::ZERO), at(F::ONE), at(F::ONE.double()));
+        set(prover, group, current);
+
+        let slope = e1 - e0;
+        if e2 - e1 != slope {
+            return None;
+        }
+        let inverse: Option<F> = slope.invert().into();
+        inverse.map(|inverse| -e0 * inverse)
+    }
+
+    /// Changes groups until no gate is broken, depth first. On failure `changed` and
+    /// the witness are back as they were.
+    fn repair(
+        &self,
+        prover: &mut MockProver<F>,
+        changed: &mut Vec<usize>,
+        budget: &mut usize,
+    ) -> bool {
+        let broken = self.broken(prover, changed);
+        if broken.is_empty() {
+            return prover.verify().is_ok();
+        }
+        if changed.len() == MAX_GROUPS {
+            return false;
+        }
+
+        // the constraint with the fewest ways out, none means a dead end
+        let (constraint, candidates) = broken
+            .into_iter()
+            .map(|constraint| (constraint, self.unchanged(constraint, changed)))
+            .min_by_key(|(_, candidates)| candidates.len())
+            .unwrap();
+
+        for g in candidates {
+            if *budget == 0 {
+                return false;
+            }
+            *budget -= 1;
+            let current = match value(prover, &self.groups[g]) {
+                Some(v) => v,
+                None => continue,
+            };
+            let solved = match self.solve(prover, constraint, g) {
+                Some(v) => v,
+                None => continue,
+            };
+            set(prover, &self.groups[g], solved);
+            changed.push(g);
+            if self.repair(prover, changed, budget) {
+                return true;
+            }
+            changed.pop();
+            set(prover, &self.groups[g], current);
+        }
+        false
+    }
+}
+
+/// The value of a group in the witness, `None` if it isn't assigned.
+fn value<F: Field>(prover: &MockProver<F>, group: &[NamedCell]) -> Option<F> {
+    let cell = &group[0];
+    match prover.advice()[cell.column.index()][cell.row] {
+        CellValue::Assigned(v) => Some(v),
+        _ => None,
+    }
+}
+
+fn set<F: FromUniformBytes<64> + Ord>(prover: &mut MockProver<F>, group: &[NamedCell], v: F) {
+    for cell in group.iter() {
+        prover.advice_mut(cell.column.index())[cell.row] = CellValue::Assigned(v);
+    }
+}
+
+/// Advice cells grouped by copy constraints. Groups containing an instance or fixed cell
+/// are kept, changing them is still worth a try: it will only fail.
+fn copy_groups<F: Field>(layout: &Layout<F>) -> Vec<Vec<NamedCell>> {
+    let classes = layout.copy_classes();
+
+    let mut groups: Vec<(AnyCell, Vec<NamedCell>)> = vec![];
+    for cell in layout.advice.iter() {
+        let key = classes.advice_root(cell);
+        match groups.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, group)) => {
+                if !group
+                    .iter()
+                    .any(|c| c.column == cell.column && c.row == cell.row)
+                {
+                    group.push(cell.clone());
+                }
+            }
+            None => groups.push((key, vec![cell.clone()])),
+        }
+    }
+
+    groups.into_iter().map(|(_, group)| group).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::{
+        circuit::Value,
+        halo2curves::{bn256::Fr, pasta::Fp},
+    };
+
+    use super::*;
+    use crate::{casino::casino0, merkle::MerkleCircuitNoHash3, mul::mul0};
+
+    fn changes(finding: &Finding, region: &str, annotation: &str) -> bool {
+        finding
+            .cells()
+            .iter()
+            .any(|c| c.region == region && c.annotation == annotation)
+    }
+
+    #[test]
+    fn mul0_out_is_free() {
+        let circuit = mul0::MultiplicationCircuit {
+            a: Value::known(Fr::from(2)),
+            b: Value::known(Fr::from(3)),
+        };
+        let findings = find_underconstrained(4, &circuit, vec![vec![Fr::from(12)]]).unwrap();
+        assert!(findings
+            .iter()
+            .any(|f| f.repairs.is_empty() && changes(f, "main region", "out")));
+    }
+
+    #[test]
+    fn casino0_deposits_are_free() {
+        let deposits = [0x300, 0x400, 0x500].map(Fr::from);
+        let circuit = casino0::CasinoCircuit {
+            deposits: deposits.iter().copied().map(Value::known).collect(),
+        };
+        let total = deposits.iter().sum::<Fr>();
+        let findings = find_underconstrained(4, &circuit, vec![vec![total]]).unwrap();
+
+        let free = findings
+            .iter()
+            .filter(|f| changes(f, "main region", "deposit"))
+            .count();
+        assert_eq!(free, deposits.len());
+        // the total is bound to the public input, it's the deposits that don't add up
+        assert!(!findings.iter().any(|f| changes(f, "main region", "out")));
+    }
+
+    #[test]
+    fn merkle_nohash3_leaf_hash_is_forged() {
+        let leaves = [2, 5, 11, 20].map(Fp::from);
+        let hash_leaf = |v: Fp| v + v;
+        let h1 = hash_leaf(leaves[0]) + hash_leaf(leaves[1]);
+        let h2 = hash_leaf(leaves[2]) + hash_leaf(leaves[3]);
+        let circuit = MerkleCircuitNoHash3 {
+            leaf: Value::known(leaves[0]),
+            path_elements: vec![Value::known(hash_leaf(leaves[1])), Value::known(h2)],
+            path_indices: vec![Value::known(Fp::from(0)), Value::known(Fp::from(0))],
+        };
+        let findings = find_underconstrained(4, &circuit, vec![vec![h1 + h2]]).unwrap();
+
+        // the forgery of the README: the leaf hash and the node hashed with it change
+        // together. The root is public, so the neighbor makes up for the difference
+        // instead of the result.
+        assert!(findings.iter().any(|f| {
+            changes(f, "assign leaf", "assign leaf hash")
+                && changes(f, "merkle prove", "copy previous node cell")
+                && changes(f, "merkle prove", "left node to be hashed")
+                && changes(f, "merkle prove", "set neighbor node")
+        }));
+    }
+}
@@ -6,10 +6,13 @@ use halo2_proofs::{
     },
     poly::Rotation,
 };
+use serde::Deserialize;
 
-#[derive(Debug, Default, Clone, Copy)]
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
 pub struct SimpleCircuit {
+    #[serde(default, deserialize_with = "crate::inputs::value")]
     pub a: Value<Fp>,
+    #[serde(default, deserialize_with = "crate::inputs::value")]
     pub b: Value<Fp>,
 }
 
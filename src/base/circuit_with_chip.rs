@@ -6,6 +6,7 @@ use halo2_proofs::{
     },
     poly::Rotation,
 };
+use serde::Deserialize;
 
 #[derive(Clone, Debug)]
 pub struct ChipSimpleConfig {
@@ -15,9 +16,11 @@ pub struct ChipSimpleConfig {
 
 pub struct ChipSimple;
 
-#[derive(Debug, Default, Clone, Copy)]
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
 pub struct SimpleCircuit {
+    #[serde(default, deserialize_with = "crate::inputs::value")]
     pub a: Value<Fp>,
+    #[serde(default, deserialize_with = "crate::inputs::value")]
     pub b: Value<Fp>,
 }
 
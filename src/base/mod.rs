@@ -0,0 +1,48 @@
+use halo2_proofs::{circuit::Value, halo2curves::bn256::Fr as Fp};
+
+use crate::scenario::{CircuitScenario, Registry, Verdict};
+
+pub mod circuit;
+pub mod circuit_with_chip;
+
+pub fn register(registry: &mut Registry) {
+    // a + b == out, and every advice value is in the table [0, 8]
+    let circuit = circuit::SimpleCircuit {
+        a: Value::known(Fp::from(2)),
+        b: Value::known(Fp::from(3)),
+    };
+    registry.register(CircuitScenario::new(
+        "base/honest",
+        circuit,
+        4,
+        vec![vec![Fp::from(5)]],
+        Verdict::Accept,
+    ));
+
+    // 4 + 5 == 9 is correct, but 9 is not in the lookup table
+    let circuit = circuit::SimpleCircuit {
+        a: Value::known(Fp::from(4)),
+        b: Value::known(Fp::from(5)),
+    };
+    registry.register(CircuitScenario::new(
+        "base/out-of-range",
+        circuit,
+        4,
+        vec![vec![Fp::from(9)]],
+        Verdict::Reject,
+    ));
+
+    // ======================================================
+    // same circuit, with the gate and the lookup moved into a chip
+    let circuit = circuit_with_chip::SimpleCircuit {
+        a: Value::known(Fp::from(2)),
+        b: Value::known(Fp::from(3)),
+    };
+    registry.register(CircuitScenario::new(
+        "base_with_chip/honest",
+        circuit,
+        4,
+        vec![vec![Fp::from(5)]],
+        Verdict::Accept,
+    ));
+}
@@ -3,9 +3,11 @@ use halo2_proofs::{
     halo2curves::bn256::Fr as Fp,
     plonk::{Advice, Circuit, Column, ConstraintSystem, ErrorFront, Instance},
 };
+use serde::Deserialize;
 
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, Deserialize)]
 pub struct CasinoCircuit {
+    #[serde(default, deserialize_with = "crate::inputs::values")]
     pub deposits: Vec<Value<Fp>>,
 }
 
@@ -4,9 +4,11 @@ use halo2_proofs::{
     plonk::{Advice, Circuit, Column, ConstraintSystem, ErrorFront, Instance, Selector},
     poly::Rotation,
 };
+use serde::Deserialize;
 
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, Deserialize)]
 pub struct CasinoCircuit {
+    #[serde(default, deserialize_with = "crate::inputs::values")]
     pub deposits: Vec<Value<Fp>>,
 }
 
@@ -6,9 +6,11 @@ use halo2_proofs::{
     },
     poly::Rotation,
 };
+use serde::Deserialize;
 
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, Deserialize)]
 pub struct CasinoCircuit {
+    #[serde(default, deserialize_with = "crate::inputs::values")]
     pub deposits: Vec<Value<Fp>>,
 }
 
@@ -0,0 +1,188 @@
+use halo2_proofs::{
+    circuit::{Layouter, SimpleFloorPlanner, Value},
+    halo2curves::bn256::Fr as Fp,
+    plonk::{Advice, Circuit, Column, ConstraintSystem, ErrorFront, Instance, Selector},
+    poly::Rotation,
+};
+use serde::Deserialize;
+
+use crate::chips::{RangeCheckChip, RangeCheckConfig};
+
+/// Deposits are `u64` balances
+pub const DEPOSIT_BITS: usize = 64;
+/// `reserves - sum(deposits)`: with the sum below `2^(64 + log2(deposits))`,
+/// a headroom of 128 bits can't wrap around the modulus
+pub const HEADROOM_BITS: usize = 128;
+const LIMB_BITS: usize = 8;
+
+/// What the public input says about the deposits.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum Claim {
+    /// The public input is the sum of the deposits.
+    #[default]
+    Total,
+    /// The public input is the reserves of the casino, which cover the deposits:
+    /// `sum(deposits) <= reserves`, without revealing the sum.
+    AtMostReserves,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+pub struct CasinoCircuit {
+    #[serde(default, deserialize_with = "crate::inputs::values")]
+    pub deposits: Vec<Value<Fp>>,
+    #[serde(default)]
+    pub claim: Claim,
+    /// Only for `Claim::AtMostReserves`, the prover needs it to compute the headroom.
+    #[serde(default, deserialize_with = "crate::inputs::value")]
+    pub reserves: Value<Fp>,
+}
+
+#[derive(Clone, Debug)]
+pub struct CasinoConfig {
+    deposits: Column<Advice>,
+    sum: Column<Advice>,
+    public: Column<Instance>,
+    selector_running_sum: Selector,
+    selector_first_row: Selector,
+    range_check: RangeCheckConfig,
+}
+
+impl Circuit<Fp> for CasinoCircuit {
+    type Config = CasinoConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self {
+            deposits: vec![Value::unknown(); self.deposits.len()],
+            claim: self.claim,
+            reserves: Value::unknown(),
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        let deposits = meta.advice_column();
+        let sum = meta.advice_column();
+        let public = meta.instance_column();
+        meta.enable_equality(deposits);
+        meta.enable_equality(sum);
+        meta.enable_equality(public);
+
+        let constants = meta.fixed_column();
+        meta.enable_constant(constants);
+
+        let selector_running_sum = meta.selector();
+        let selector_first_row = meta.selector();
+
+        meta.create_gate("first row", |meta| {
+            let s = meta.query_selector(selector_first_row);
+            let deposit = meta.query_advice(deposits, Rotation::cur());
+            let sum = meta.query_advice(sum, Rotation::cur());
+            vec![s.clone() * deposit, s * sum]
+        });
+
+        meta.create_gate("running sum", |meta| {
+            let s = meta.query_selector(selector_running_sum);
+
+            let dcur = meta.query_advice(deposits, Rotation::cur());
+            let sumprev = meta.query_advice(sum, Rotation::prev());
+            let sumcur = meta.query_advice(sum, Rotation::cur());
+
+            vec![s * (sumprev + dcur - sumcur)]
+        });
+
+        // the limbs share the columns of the sum, the range check has its own regions
+        let shift = meta.fixed_column();
+        let range_check = RangeCheckChip::<Fp, LIMB_BITS>::configure(meta, deposits, sum, shift);
+
+        CasinoConfig {
+            deposits,
+            sum,
+            public,
+            selector_running_sum,
+            selector_first_row,
+            range_check,
+        }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), ErrorFront> {
+        // 256 rows instead of 1000, for deposits up to 2^64 instead of 1000
+        let range_check = RangeCheckChip::<Fp, LIMB_BITS>::construct(config.range_check);
+        range_check.load_table(layouter.namespace(|| "limb table"))?;
+
+        let (deposits, headroom, out) = layouter.assign_region(
+            || "main region",
+            |mut region| {
+                // values should be 0 on the first row
+                config.selector_first_row.enable(&mut region, 0)?;
+                region.assign_advice(
+                    || "deposit0",
+                    config.deposits,
+                    0,
+                    || Value::known(Fp::zero()),
+                )?;
+                let mut total =
+                    region.assign_advice(|| "sum0", config.sum, 0, || Value::known(Fp::zero()))?;
+
+                let mut deposits = vec![];
+                let mut sum = Value::known(Fp::zero());
+
+                for (i, deposit) in self.deposits.iter().enumerate() {
+                    let row = i + 1;
+                    // unlike casino2, the running sum starts right after the first row
+                    config.selector_running_sum.enable(&mut region, row)?;
+
+                    deposits.push(region.assign_advice(
+                        || "deposit",
+                        config.deposits,
+                        row,
+                        || *deposit,
+                    )?);
+
+                    sum = sum + *deposit;
+                    total = region.assign_advice(|| "sum", config.sum, row, || sum)?;
+                }
+
+                // one more row of the running sum: the headroom is a deposit that
+                // brings the sum up to the reserves
+                let headroom = match self.claim {
+                    Claim::Total => None,
+                    Claim::AtMostReserves => {
+                        let row = self.deposits.len() + 1;
+                        config.selector_running_sum.enable(&mut region, row)?;
+                        let headroom = region.assign_advice(
+                            || "headroom",
+                            config.deposits,
+                            row,
+                            || self.reserves - sum,
+                        )?;
+                        total = region.assign_advice(
+                            || "reserves",
+                            config.sum,
+                            row,
+                            || self.reserves,
+                        )?;
+                        Some(headroom)
+                    }
+                };
+
+                Ok((deposits, headroom, total))
+            },
+        )?;
+
+        for deposit in deposits.iter() {
+            range_check.range_check(layouter.namespace(|| "deposit"), deposit, DEPOSIT_BITS)?;
+        }
+        // a "negative" headroom is close to the modulus, way more than 128 bits
+        if let Some(headroom) = headroom {
+            range_check.range_check(layouter.namespace(|| "headroom"), &headroom, HEADROOM_BITS)?;
+        }
+
+        layouter.constrain_instance(out.cell(), config.public, 0)?;
+
+        Ok(())
+    }
+}
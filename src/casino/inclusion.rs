@@ -0,0 +1,81 @@
+use halo2_poseidon::poseidon::primitives::Spec;
+use halo2_proofs::{
+    circuit::{Layouter, SimpleFloorPlanner, Value},
+    halo2curves::pasta::Fp,
+    plonk::{Circuit, ConstraintSystem, ErrorFront},
+};
+use serde::Deserialize;
+use std::marker::PhantomData;
+
+use super::sum_tree::{SumTreeChip, SumTreeConfig};
+
+/// Proves that the `(id, balance)` leaf of a user is in the sum tree of a `SolvencyCircuit`,
+/// with the same public root hash and total.
+#[derive(Debug, Default, Clone, Deserialize)]
+pub struct InclusionCircuit<S: Spec<Fp, WIDTH, RATE>, const WIDTH: usize, const RATE: usize> {
+    #[serde(default, deserialize_with = "crate::inputs::value")]
+    pub id: Value<Fp>,
+    #[serde(default, deserialize_with = "crate::inputs::value")]
+    pub balance: Value<Fp>,
+    /// hashes of the siblings, from the leaf to the root
+    #[serde(default, deserialize_with = "crate::inputs::values")]
+    pub path_hashes: Vec<Value<Fp>>,
+    /// sums of the siblings, from the leaf to the root
+    #[serde(default, deserialize_with = "crate::inputs::values")]
+    pub path_sums: Vec<Value<Fp>>,
+    /// 1 when the node is the right child
+    #[serde(default, deserialize_with = "crate::inputs::values")]
+    pub path_indices: Vec<Value<Fp>>,
+    #[serde(skip)]
+    pub _spec: PhantomData<S>,
+}
+
+impl<S: Spec<Fp, WIDTH, RATE>, const WIDTH: usize, const RATE: usize> Circuit<Fp>
+    for InclusionCircuit<S, WIDTH, RATE>
+{
+    type Config = SumTreeConfig<WIDTH, RATE>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self {
+            id: Value::unknown(),
+            balance: Value::unknown(),
+            path_hashes: vec![Value::unknown(); self.path_hashes.len()],
+            path_sums: vec![Value::unknown(); self.path_sums.len()],
+            path_indices: vec![Value::unknown(); self.path_indices.len()],
+            _spec: PhantomData,
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        SumTreeChip::<S, WIDTH, RATE>::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), ErrorFront> {
+        let depth = self.path_hashes.len();
+        if self.path_sums.len() != depth || self.path_indices.len() != depth {
+            return Err(ErrorFront::Synthesis);
+        }
+        let chip = SumTreeChip::<S, WIDTH, RATE>::construct(config);
+        chip.load_table(layouter.namespace(|| "table"))?;
+
+        // the sums of the siblings are not range checked: the root binds them to the nodes
+        // of the solvency tree, where every balance was
+        let mut node = chip.leaf(layouter.namespace(|| "leaf"), self.id, self.balance)?;
+        for i in 0..depth {
+            node = chip.parent_with_sibling(
+                layouter.namespace(|| "prove tree"),
+                &node,
+                self.path_hashes[i],
+                self.path_sums[i],
+                self.path_indices[i],
+            )?;
+        }
+
+        chip.expose_root(layouter.namespace(|| "root"), &node)
+    }
+}
@@ -1,14 +1,27 @@
+use halo2_poseidon::poseidon::primitives::P128Pow5T3 as OrchardNullifier;
 use halo2_proofs::{
     circuit::Value,
-    dev::{CellValue, MockProver},
-    halo2curves::bn256::Fr as Fp,
+    halo2curves::{bn256::Fr as Fp, pasta::pallas::Base as PallasFp},
 };
+use std::marker::PhantomData;
 
-mod casino0;
-mod casino1;
-mod casino2;
+use crate::{
+    scenario::{CircuitScenario, Registry, Verdict},
+    tamper::{CellTarget, Tamper},
+};
+
+pub mod casino0;
+pub mod casino1;
+pub mod casino2;
+pub mod casino3;
+pub mod inclusion;
+pub mod solvency;
+pub mod sum_tree;
+
+pub use inclusion::InclusionCircuit;
+pub use solvency::SolvencyCircuit;
 
-pub fn casino() {
+pub fn register(registry: &mut Registry) {
     // this is the total amount of money in the casino
     // that's what the owner needs to prove
     let total = Fp::from(0x950);
@@ -21,14 +34,19 @@ pub fn casino() {
     let circuit = casino0::CasinoCircuit {
         deposits: deposits.clone(),
     };
-    let mut prover = MockProver::run(4, &circuit, vec![vec![total]]).unwrap();
-
     // if we decide to cheat, we can modify the advice column
-    let advice = prover.advice_mut(0);
-    // set the last deposit to the `total`
-    advice[3] = CellValue::Assigned(total);
     // and the proof succeeds
-    assert!(prover.verify().is_ok());
+    registry.register(
+        CircuitScenario::new(
+            "casino0/tampered-total",
+            circuit,
+            4,
+            vec![vec![total]],
+            Verdict::Accept,
+        )
+        // set the `out` cell, right after the last deposit, to the `total`
+        .with_tamper(Tamper::new().set(CellTarget::new("main region", "out"), total)),
+    );
 
     // ======================================================
     //in `casino1` we added gate, so the previous technique won't work anymore. But...
@@ -42,17 +60,27 @@ pub fn casino() {
     let circuit = casino1::CasinoCircuit {
         deposits: deposits.clone(),
     };
-    let prover = MockProver::run(4, &circuit, vec![vec![total]]).unwrap();
     // the proof succeeds, but the input is not valid!
-    assert!(prover.verify().is_ok());
+    registry.register(CircuitScenario::new(
+        "casino1/field-overflow",
+        circuit,
+        4,
+        vec![vec![total]],
+        Verdict::Accept,
+    ));
 
     // ======================================================
     // in `casino2` we added a lookup table, so the previous technique won't work anymore.
     // notice that we need to make K higher since we now use at least 1000 rows
     let circuit = casino2::CasinoCircuit { deposits };
-    let prover = MockProver::run(10, &circuit, vec![vec![total]]).unwrap();
     // the proof fails! 🙏
-    assert!(prover.verify().is_err());
+    registry.register(CircuitScenario::new(
+        "casino2/field-overflow",
+        circuit,
+        10,
+        vec![vec![total]],
+        Verdict::Reject,
+    ));
 
     // let's try with deposits within range
     let deposits = vec![
@@ -61,9 +89,14 @@ pub fn casino() {
         Value::known(Fp::from(500)),
     ];
     let circuit = casino2::CasinoCircuit { deposits };
-    let prover = MockProver::run(10, &circuit, vec![vec![total]]).unwrap();
     // the proof fails again, since the deposits do not equal to the total
-    assert!(prover.verify().is_err());
+    registry.register(CircuitScenario::new(
+        "casino2/wrong-total",
+        circuit,
+        10,
+        vec![vec![total]],
+        Verdict::Reject,
+    ));
 
     // we make the deposits equal to the total (0x950 == 2384)
     let deposits = vec![
@@ -72,6 +105,197 @@ pub fn casino() {
         Value::known(Fp::from(884)),
     ];
     let circuit = casino2::CasinoCircuit { deposits };
-    let prover = MockProver::run(10, &circuit, vec![vec![total]]).unwrap();
-    assert!(prover.verify().is_ok());
+    registry.register(CircuitScenario::new(
+        "casino2/honest",
+        circuit,
+        10,
+        vec![vec![total]],
+        Verdict::Accept,
+    ));
+
+    // ======================================================
+    // `casino3` range checks the deposits with 8-bit limbs instead of a 0..1000 table:
+    // realistic u64 balances, with k = 9 instead of 10
+    let big = Fp::from(u64::MAX);
+    let deposits = vec![
+        Value::known(big),
+        Value::known(Fp::from(0x300)),
+        Value::known(Fp::from(0x400)),
+    ];
+    let circuit = casino3::CasinoCircuit {
+        deposits,
+        ..Default::default()
+    };
+    registry.register(CircuitScenario::new(
+        "casino3/honest",
+        circuit,
+        9,
+        vec![vec![big + Fp::from(0x700)]],
+        Verdict::Accept,
+    ));
+
+    // the overflowing deposit of casino1 is way more than 64 bits
+    let deposits = vec![
+        Value::known(Fp::from(0x300)),
+        Value::known(Fp::from(0x400)),
+        Value::known(Fp::from(0x500)),
+        Value::known(total - Fp::from(0xc00)),
+    ];
+    let circuit = casino3::CasinoCircuit {
+        deposits,
+        ..Default::default()
+    };
+    registry.register(CircuitScenario::new(
+        "casino3/field-overflow",
+        circuit,
+        9,
+        vec![vec![total]],
+        Verdict::Reject,
+    ));
+
+    // one bit too many
+    let deposits = vec![Value::known(big + Fp::one())];
+    let circuit = casino3::CasinoCircuit {
+        deposits,
+        ..Default::default()
+    };
+    registry.register(CircuitScenario::new(
+        "casino3/65-bit-deposit",
+        circuit,
+        9,
+        vec![vec![big + Fp::one()]],
+        Verdict::Reject,
+    ));
+
+    // ======================================================
+    // `casino3` can also prove the deposits are covered by public reserves,
+    // without revealing their sum
+    let deposits = vec![
+        Value::known(big),
+        Value::known(Fp::from(0x300)),
+        Value::known(Fp::from(0x400)),
+    ];
+    let reserves = |reserves: Fp| casino3::CasinoCircuit {
+        deposits: deposits.clone(),
+        claim: casino3::Claim::AtMostReserves,
+        reserves: Value::known(reserves),
+    };
+    let sum = big + Fp::from(0x700);
+    registry.register(CircuitScenario::new(
+        "casino3/reserves-cover",
+        reserves(sum + Fp::from(1000)),
+        9,
+        vec![vec![sum + Fp::from(1000)]],
+        Verdict::Accept,
+    ));
+    registry.register(CircuitScenario::new(
+        "casino3/reserves-exact",
+        reserves(sum),
+        9,
+        vec![vec![sum]],
+        Verdict::Accept,
+    ));
+    // one short: the headroom is -1, which wraps around to p - 1
+    registry.register(CircuitScenario::new(
+        "casino3/reserves-short",
+        reserves(sum - Fp::one()),
+        9,
+        vec![vec![sum - Fp::one()]],
+        Verdict::Reject,
+    ));
+
+    solvency(registry);
+}
+
+/// The deposits in a Poseidon Merkle sum tree, so each user can check their own deposit.
+fn solvency(registry: &mut Registry) {
+    let users = [
+        (PallasFp::from(1), PallasFp::from(u64::MAX)),
+        (PallasFp::from(2), PallasFp::from(0x300)),
+        (PallasFp::from(3), PallasFp::from(0x400)),
+        (PallasFp::from(4), PallasFp::from(0x500)),
+    ];
+    let tree = sum_tree::native_tree::<OrchardNullifier, 3, 2>(&users);
+    let (root, total) = tree[2][0];
+
+    let circuit = SolvencyCircuit::<OrchardNullifier, 3, 2> {
+        ids: users.iter().map(|&(id, _)| Value::known(id)).collect(),
+        balances: users.iter().map(|&(_, b)| Value::known(b)).collect(),
+        _spec: PhantomData,
+    };
+    registry.register(CircuitScenario::new(
+        "casino_solvency/honest",
+        circuit.clone(),
+        10,
+        vec![vec![root, total]],
+        Verdict::Accept,
+    ));
+
+    // lower the total at the root, the sum gate catches it
+    registry.register(
+        CircuitScenario::new(
+            "casino_solvency/tampered-total",
+            circuit,
+            10,
+            vec![vec![root, total - PallasFp::from(0x500)]],
+            Verdict::Reject,
+        )
+        .with_tamper(Tamper::new().set(
+            CellTarget::new("node", "sum").nth(2),
+            total - PallasFp::from(0x500),
+        )),
+    );
+
+    // the casino1 trick: a negative balance hides a debt of the casino,
+    // the tree is consistent but the balance is not 64 bits
+    let claimed = PallasFp::from(0x950);
+    let forged = [
+        users[1],
+        users[2],
+        users[3],
+        (PallasFp::from(5), claimed - PallasFp::from(0xc00)),
+    ];
+    let forged_tree = sum_tree::native_tree::<OrchardNullifier, 3, 2>(&forged);
+    let circuit = SolvencyCircuit::<OrchardNullifier, 3, 2> {
+        ids: forged.iter().map(|&(id, _)| Value::known(id)).collect(),
+        balances: forged.iter().map(|&(_, b)| Value::known(b)).collect(),
+        _spec: PhantomData,
+    };
+    registry.register(CircuitScenario::new(
+        "casino_solvency/negative-balance",
+        circuit,
+        10,
+        vec![vec![forged_tree[2][0].0, claimed]],
+        Verdict::Reject,
+    ));
+
+    // user 3 is the left child of the right child of the root
+    let (id, balance) = users[2];
+    let inclusion = |balance: PallasFp| InclusionCircuit::<OrchardNullifier, 3, 2> {
+        id: Value::known(id),
+        balance: Value::known(balance),
+        path_hashes: vec![Value::known(tree[0][3].0), Value::known(tree[1][0].0)],
+        path_sums: vec![Value::known(tree[0][3].1), Value::known(tree[1][0].1)],
+        path_indices: vec![
+            Value::known(PallasFp::from(0)),
+            Value::known(PallasFp::from(1)),
+        ],
+        _spec: PhantomData,
+    };
+    registry.register(CircuitScenario::new(
+        "casino_inclusion/honest",
+        inclusion(balance),
+        10,
+        vec![vec![root, total]],
+        Verdict::Accept,
+    ));
+
+    // the user claims more than what the casino committed to
+    registry.register(CircuitScenario::new(
+        "casino_inclusion/inflated-balance",
+        inclusion(balance + PallasFp::from(0x100)),
+        10,
+        vec![vec![root, total]],
+        Verdict::Reject,
+    ));
 }
@@ -0,0 +1,71 @@
+use halo2_poseidon::poseidon::primitives::Spec;
+use halo2_proofs::{
+    circuit::{Layouter, SimpleFloorPlanner, Value},
+    halo2curves::pasta::Fp,
+    plonk::{Circuit, ConstraintSystem, ErrorFront},
+};
+use serde::Deserialize;
+use std::marker::PhantomData;
+
+use super::sum_tree::{SumTreeChip, SumTreeConfig};
+
+/// Proof of solvency: the deposits of every user are committed to in a Merkle sum tree,
+/// whose root hash and total are public.
+/// Users then check their own deposit with an `InclusionCircuit` against the same root.
+#[derive(Debug, Default, Clone, Deserialize)]
+pub struct SolvencyCircuit<S: Spec<Fp, WIDTH, RATE>, const WIDTH: usize, const RATE: usize> {
+    #[serde(default, deserialize_with = "crate::inputs::values")]
+    pub ids: Vec<Value<Fp>>,
+    /// one per id, the number of users must be a power of two
+    #[serde(default, deserialize_with = "crate::inputs::values")]
+    pub balances: Vec<Value<Fp>>,
+    #[serde(skip)]
+    pub _spec: PhantomData<S>,
+}
+
+impl<S: Spec<Fp, WIDTH, RATE>, const WIDTH: usize, const RATE: usize> Circuit<Fp>
+    for SolvencyCircuit<S, WIDTH, RATE>
+{
+    type Config = SumTreeConfig<WIDTH, RATE>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self {
+            ids: vec![Value::unknown(); self.ids.len()],
+            balances: vec![Value::unknown(); self.balances.len()],
+            _spec: PhantomData,
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        SumTreeChip::<S, WIDTH, RATE>::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), ErrorFront> {
+        if self.ids.len() != self.balances.len() || !self.ids.len().is_power_of_two() {
+            return Err(ErrorFront::Synthesis);
+        }
+        let chip = SumTreeChip::<S, WIDTH, RATE>::construct(config);
+        chip.load_table(layouter.namespace(|| "table"))?;
+
+        let mut level = self
+            .ids
+            .iter()
+            .zip(&self.balances)
+            .map(|(id, balance)| chip.leaf(layouter.namespace(|| "leaf"), *id, *balance))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        while level.len() > 1 {
+            level = level
+                .chunks(2)
+                .map(|pair| chip.parent(layouter.namespace(|| "parent"), &pair[0], &pair[1]))
+                .collect::<Result<Vec<_>, _>>()?;
+        }
+
+        chip.expose_root(layouter.namespace(|| "root"), &level[0])
+    }
+}
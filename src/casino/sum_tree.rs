@@ -0,0 +1,327 @@
+//! Merkle sum tree of the casino deposits, shared by the solvency and inclusion circuits.
+//!
+//! Every node is a `(hash, sum)` pair:
+//! - a leaf is `(Poseidon(id, balance), balance)`
+//! - a parent is `(Poseidon(left.hash, left.sum, right.hash, right.sum), left.sum + right.sum)`
+//!
+//! The sums are hashed with the hashes, so changing a sum changes the root.
+//! Leaves hash 2 elements and nodes 4, `ConstantLength` puts the length in the
+//! initial state, so a node can't pass for a leaf.
+
+use halo2_poseidon::poseidon::{
+    primitives::{self as poseidon, ConstantLength, Spec},
+    Hash, Pow5Chip, Pow5Config,
+};
+use halo2_proofs::{
+    arithmetic::Field,
+    circuit::{AssignedCell, Layouter, Value},
+    halo2curves::pasta::Fp,
+    plonk::{Advice, Column, ConstraintSystem, ErrorFront, Expression, Instance, Selector},
+    poly::Rotation,
+};
+use std::marker::PhantomData;
+
+use crate::chips::{RangeCheckChip, RangeCheckConfig};
+
+/// Balances are `u64`
+pub const BALANCE_BITS: usize = 64;
+const LIMB_BITS: usize = 8;
+
+/// Columns of a node row: hash and sum of the left child, hash and sum of the right child,
+/// and the sum of the parent. In a swap region, the first row holds the node, its sibling
+/// and the swap bit, and the second row is a node row.
+#[derive(Debug, Clone)]
+pub struct SumTreeConfig<const WIDTH: usize, const RATE: usize> {
+    pub pow5config: Pow5Config<Fp, WIDTH, RATE>,
+    pub advice: [Column<Advice>; 5],
+    /// the root hash on row 0, the total on row 1
+    pub public: Column<Instance>,
+    pub range_check: RangeCheckConfig,
+    q_sum: Selector,
+    q_swap: Selector,
+}
+
+/// A node of the tree, as assigned in the circuit.
+#[derive(Debug, Clone)]
+pub struct SumNode {
+    pub hash: AssignedCell<Fp, Fp>,
+    pub sum: AssignedCell<Fp, Fp>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SumTreeChip<S: Spec<Fp, WIDTH, RATE>, const WIDTH: usize, const RATE: usize> {
+    config: SumTreeConfig<WIDTH, RATE>,
+    _spec: PhantomData<S>,
+}
+
+impl<S: Spec<Fp, WIDTH, RATE>, const WIDTH: usize, const RATE: usize> SumTreeChip<S, WIDTH, RATE> {
+    pub fn construct(config: SumTreeConfig<WIDTH, RATE>) -> Self {
+        Self {
+            config,
+            _spec: PhantomData,
+        }
+    }
+
+    pub fn configure(meta: &mut ConstraintSystem<Fp>) -> SumTreeConfig<WIDTH, RATE> {
+        let state = (0..WIDTH).map(|_| meta.advice_column()).collect::<Vec<_>>();
+        let partial_sbox = meta.advice_column();
+
+        let rc_a = (0..WIDTH).map(|_| meta.fixed_column()).collect::<Vec<_>>();
+        let rc_b = (0..WIDTH).map(|_| meta.fixed_column()).collect::<Vec<_>>();
+
+        // also the constant column of the range check
+        meta.enable_constant(rc_b[0]);
+
+        let pow5config = Pow5Chip::configure::<S>(
+            meta,
+            state.try_into().unwrap(),
+            partial_sbox,
+            rc_a.try_into().unwrap(),
+            rc_b.try_into().unwrap(),
+        );
+
+        let public = meta.instance_column();
+        meta.enable_equality(public);
+
+        let advice = [(); 5].map(|_| meta.advice_column());
+        for column in advice {
+            meta.enable_equality(column);
+        }
+
+        let q_sum = meta.selector();
+        let q_swap = meta.selector();
+
+        meta.create_gate("node sum", |meta| {
+            let s = meta.query_selector(q_sum);
+            let left_sum = meta.query_advice(advice[1], Rotation::cur());
+            let right_sum = meta.query_advice(advice[3], Rotation::cur());
+            let sum = meta.query_advice(advice[4], Rotation::cur());
+            vec![s * (left_sum + right_sum - sum)]
+        });
+
+        // same as the swap of `merkle_circuit`, for both the hash and the sum
+        meta.create_gate("swap", |meta| {
+            let s = meta.query_selector(q_swap);
+            let bit = meta.query_advice(advice[4], Rotation::cur());
+
+            let mut constraints =
+                vec![s.clone() * bit.clone() * (Expression::Constant(Fp::ONE) - bit.clone())];
+            for (node, sibling) in [(advice[0], advice[2]), (advice[1], advice[3])] {
+                let node_cur = meta.query_advice(node, Rotation::cur());
+                let sibling_cur = meta.query_advice(sibling, Rotation::cur());
+                let left_next = meta.query_advice(node, Rotation::next());
+                let right_next = meta.query_advice(sibling, Rotation::next());
+
+                constraints.push(
+                    s.clone()
+                        * ((sibling_cur.clone() - node_cur.clone()) * bit.clone()
+                            + node_cur.clone()
+                            - left_next),
+                );
+                constraints.push(
+                    s.clone()
+                        * ((node_cur - sibling_cur.clone()) * bit.clone() + sibling_cur
+                            - right_next),
+                );
+            }
+            constraints
+        });
+
+        // the limbs share the columns of the right child
+        let shift = meta.fixed_column();
+        let range_check =
+            RangeCheckChip::<Fp, LIMB_BITS>::configure(meta, advice[2], advice[3], shift);
+
+        SumTreeConfig {
+            pow5config,
+            advice,
+            public,
+            range_check,
+            q_sum,
+            q_swap,
+        }
+    }
+
+    /// Must be called once, before any leaf.
+    pub fn load_table(&self, layouter: impl Layouter<Fp>) -> Result<(), ErrorFront> {
+        RangeCheckChip::<Fp, LIMB_BITS>::construct(self.config.range_check.clone())
+            .load_table(layouter)
+    }
+
+    /// Hashes a leaf, after checking the balance fits in `BALANCE_BITS` bits.
+    /// Without the range check, a negative balance would hide a debt in the total.
+    pub fn leaf(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        id: Value<Fp>,
+        balance: Value<Fp>,
+    ) -> Result<SumNode, ErrorFront> {
+        let config = &self.config;
+        let (id, balance) = layouter.assign_region(
+            || "leaf",
+            |mut region| {
+                let id = region.assign_advice(|| "id", config.advice[0], 0, || id)?;
+                let balance =
+                    region.assign_advice(|| "balance", config.advice[1], 0, || balance)?;
+                Ok((id, balance))
+            },
+        )?;
+
+        RangeCheckChip::<Fp, LIMB_BITS>::construct(config.range_check.clone()).range_check(
+            layouter.namespace(|| "balance range"),
+            &balance,
+            BALANCE_BITS,
+        )?;
+
+        let hasher = Hash::<_, _, S, ConstantLength<2>, WIDTH, RATE>::init(
+            Pow5Chip::construct(config.pow5config.clone()),
+            layouter.namespace(|| "init"),
+        )?;
+        let hash = hasher.hash(layouter.namespace(|| "hash leaf"), [id, balance.clone()])?;
+
+        Ok(SumNode { hash, sum: balance })
+    }
+
+    /// The parent of two nodes we already have, when building the whole tree.
+    pub fn parent(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        left: &SumNode,
+        right: &SumNode,
+    ) -> Result<SumNode, ErrorFront> {
+        let config = &self.config;
+        let (children, sum) = layouter.assign_region(
+            || "node",
+            |mut region| {
+                config.q_sum.enable(&mut region, 0)?;
+                let children = [&left.hash, &left.sum, &right.hash, &right.sum]
+                    .into_iter()
+                    .zip(config.advice)
+                    .map(|(cell, column)| cell.copy_advice(|| "child", &mut region, column, 0))
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                let sum = left.sum.value().copied() + right.sum.value().copied();
+                let sum = region.assign_advice(|| "sum", config.advice[4], 0, || sum)?;
+                Ok((children, sum))
+            },
+        )?;
+
+        self.hash_node(layouter, children, sum)
+    }
+
+    /// The parent of `node` and a witnessed sibling, `swap_bit` is 1 when `node` is the right child.
+    pub fn parent_with_sibling(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        node: &SumNode,
+        sibling_hash: Value<Fp>,
+        sibling_sum: Value<Fp>,
+        swap_bit: Value<Fp>,
+    ) -> Result<SumNode, ErrorFront> {
+        let config = &self.config;
+        let (children, sum) = layouter.assign_region(
+            || "node with sibling",
+            |mut region| {
+                config.q_swap.enable(&mut region, 0)?;
+                config.q_sum.enable(&mut region, 1)?;
+
+                node.hash
+                    .copy_advice(|| "hash", &mut region, config.advice[0], 0)?;
+                node.sum
+                    .copy_advice(|| "sum", &mut region, config.advice[1], 0)?;
+                region.assign_advice(|| "sibling hash", config.advice[2], 0, || sibling_hash)?;
+                region.assign_advice(|| "sibling sum", config.advice[3], 0, || sibling_sum)?;
+                region.assign_advice(|| "swap bit", config.advice[4], 0, || swap_bit)?;
+
+                let node = [node.hash.value().copied(), node.sum.value().copied()];
+                let sibling = [sibling_hash, sibling_sum];
+                let mut children = [node[0], node[1], sibling[0], sibling[1]];
+                swap_bit.map(|bit| {
+                    if bit != Fp::ZERO {
+                        children = [sibling[0], sibling[1], node[0], node[1]];
+                    }
+                });
+
+                let children = children
+                    .into_iter()
+                    .zip(config.advice)
+                    .map(|(value, column)| region.assign_advice(|| "child", column, 1, || value))
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                let sum = children[1].value().copied() + children[3].value().copied();
+                let sum = region.assign_advice(|| "sum", config.advice[4], 1, || sum)?;
+                Ok((children, sum))
+            },
+        )?;
+
+        self.hash_node(layouter, children, sum)
+    }
+
+    fn hash_node(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        children: Vec<AssignedCell<Fp, Fp>>,
+        sum: AssignedCell<Fp, Fp>,
+    ) -> Result<SumNode, ErrorFront> {
+        let hasher = Hash::<_, _, S, ConstantLength<4>, WIDTH, RATE>::init(
+            Pow5Chip::construct(self.config.pow5config.clone()),
+            layouter.namespace(|| "init"),
+        )?;
+        let hash = hasher.hash(
+            layouter.namespace(|| "hash node"),
+            children.try_into().unwrap(),
+        )?;
+        Ok(SumNode { hash, sum })
+    }
+
+    /// Constrains the root hash and the total to the public inputs.
+    pub fn expose_root(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        root: &SumNode,
+    ) -> Result<(), ErrorFront> {
+        layouter.constrain_instance(root.hash.cell(), self.config.public, 0)?;
+        layouter.constrain_instance(root.sum.cell(), self.config.public, 1)
+    }
+}
+
+/// Same as `SumTreeChip::leaf`, outside of the circuit.
+pub fn native_leaf<S: Spec<Fp, WIDTH, RATE>, const WIDTH: usize, const RATE: usize>(
+    id: Fp,
+    balance: Fp,
+) -> (Fp, Fp) {
+    let hash = poseidon::Hash::<_, S, ConstantLength<2>, WIDTH, RATE>::init().hash([id, balance]);
+    (hash, balance)
+}
+
+/// Same as `SumTreeChip::parent`, outside of the circuit.
+pub fn native_parent<S: Spec<Fp, WIDTH, RATE>, const WIDTH: usize, const RATE: usize>(
+    left: (Fp, Fp),
+    right: (Fp, Fp),
+) -> (Fp, Fp) {
+    let hash = poseidon::Hash::<_, S, ConstantLength<4>, WIDTH, RATE>::init()
+        .hash([left.0, left.1, right.0, right.1]);
+    (hash, left.1 + right.1)
+}
+
+/// Every level of the tree of `(id, balance)` leaves, from the leaves to the root.
+/// The number of leaves must be a power of two.
+pub fn native_tree<S: Spec<Fp, WIDTH, RATE>, const WIDTH: usize, const RATE: usize>(
+    leaves: &[(Fp, Fp)],
+) -> Vec<Vec<(Fp, Fp)>> {
+    assert!(leaves.len().is_power_of_two());
+    let mut levels = vec![leaves
+        .iter()
+        .map(|&(id, balance)| native_leaf::<S, WIDTH, RATE>(id, balance))
+        .collect::<Vec<_>>()];
+    while levels.last().unwrap().len() > 1 {
+        let level = levels
+            .last()
+            .unwrap()
+            .chunks(2)
+            .map(|pair| native_parent::<S, WIDTH, RATE>(pair[0], pair[1]))
+            .collect();
+        levels.push(level);
+    }
+    levels
+}
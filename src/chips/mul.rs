@@ -0,0 +1,90 @@
+use halo2_proofs::{
+    circuit::{AssignedCell, Layouter, Value},
+    halo2curves::ff::PrimeField,
+    plonk::{Advice, Column, ConstraintSystem, ErrorFront, Selector},
+    poly::Rotation,
+};
+use std::marker::PhantomData;
+
+/// The layout of `mul2`: `a`, `b` and `a * b` on three consecutive rows of one advice column.
+#[derive(Clone, Debug)]
+pub struct MultiplicationConfig {
+    pub advice: Column<Advice>,
+    selector: Selector,
+}
+
+#[derive(Clone, Debug)]
+pub struct MultiplicationChip<F: PrimeField> {
+    config: MultiplicationConfig,
+    _marker: PhantomData<F>,
+}
+
+impl<F: PrimeField> MultiplicationChip<F> {
+    pub fn construct(config: MultiplicationConfig) -> Self {
+        Self {
+            config,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn configure(
+        meta: &mut ConstraintSystem<F>,
+        advice: Column<Advice>,
+    ) -> MultiplicationConfig {
+        meta.enable_equality(advice);
+        let selector = meta.selector();
+
+        // that's the gate `mul1` was missing
+        meta.create_gate("mul gate", |meta| {
+            let s = meta.query_selector(selector);
+            let a = meta.query_advice(advice, Rotation(0));
+            let b = meta.query_advice(advice, Rotation(1));
+            let c = meta.query_advice(advice, Rotation(2));
+            vec![s * (a * b - c)]
+        });
+
+        MultiplicationConfig { advice, selector }
+    }
+
+    pub fn load_private(
+        &self,
+        mut layouter: impl Layouter<F>,
+        value: Value<F>,
+    ) -> Result<AssignedCell<F, F>, ErrorFront> {
+        layouter.assign_region(
+            || "load private",
+            |mut region| region.assign_advice(|| "private", self.config.advice, 0, || value),
+        )
+    }
+
+    /// `a * b`, with `a` and `b` copied into the region so the result stays tied to them.
+    pub fn mul(
+        &self,
+        mut layouter: impl Layouter<F>,
+        a: &AssignedCell<F, F>,
+        b: &AssignedCell<F, F>,
+    ) -> Result<AssignedCell<F, F>, ErrorFront> {
+        layouter.assign_region(
+            || "mul",
+            |mut region| {
+                self.config.selector.enable(&mut region, 0)?;
+
+                let a = a.copy_advice(|| "a", &mut region, self.config.advice, 0)?;
+                let b = b.copy_advice(|| "b", &mut region, self.config.advice, 1)?;
+                region.assign_advice(|| "out", self.config.advice, 2, || a.value() * b.value())
+            },
+        )
+    }
+
+    /// The product of all `factors`, one `mul` region per factor after the first.
+    pub fn product(
+        &self,
+        mut layouter: impl Layouter<F>,
+        factors: &[AssignedCell<F, F>],
+    ) -> Result<AssignedCell<F, F>, ErrorFront> {
+        let (first, rest) = factors.split_first().ok_or(ErrorFront::Synthesis)?;
+        rest.iter().try_fold(first.clone(), |acc, factor| {
+            self.mul(layouter.namespace(|| "product"), &acc, factor)
+        })
+    }
+}
@@ -0,0 +1,172 @@
+use halo2_proofs::{
+    circuit::{AssignedCell, Layouter, Value},
+    halo2curves::ff::PrimeField,
+    plonk::{
+        Advice, Column, ConstraintSystem, ErrorFront, Expression, Fixed, Selector, TableColumn,
+    },
+    poly::Rotation,
+};
+use std::marker::PhantomData;
+
+/// Columns of a range check region, one row per limb, most significant first:
+/// - `limb`: the limb, looked up in the `LIMB_BITS` table
+/// - `z`: the limbs so far as an integer, `z_prev * 2^LIMB_BITS + limb`, the last row is the value
+/// - `shift`: `2^(LIMB_BITS - r)` on the row of a top limb of only `r` bits, looked up again
+///   once multiplied by it, so it can't use more than `r` bits
+///
+/// The region starts with a row holding `z = 0`.
+#[derive(Clone, Debug)]
+pub struct RangeCheckConfig {
+    limb: Column<Advice>,
+    z: Column<Advice>,
+    shift: Column<Fixed>,
+    table: TableColumn,
+    q_running: Selector,
+    q_limb: Selector,
+    q_top: Selector,
+}
+
+/// Checks a value fits in a given number of bits, with a table of `2^LIMB_BITS` rows
+/// whatever the number of bits.
+#[derive(Clone, Debug)]
+pub struct RangeCheckChip<F: PrimeField, const LIMB_BITS: usize> {
+    config: RangeCheckConfig,
+    _marker: PhantomData<F>,
+}
+
+impl<F: PrimeField, const LIMB_BITS: usize> RangeCheckChip<F, LIMB_BITS> {
+    pub fn construct(config: RangeCheckConfig) -> Self {
+        Self {
+            config,
+            _marker: PhantomData,
+        }
+    }
+
+    /// The circuit must also enable a constant column, the first `z` is a constant.
+    pub fn configure(
+        meta: &mut ConstraintSystem<F>,
+        limb: Column<Advice>,
+        z: Column<Advice>,
+        shift: Column<Fixed>,
+    ) -> RangeCheckConfig {
+        meta.enable_equality(z);
+
+        let table = meta.lookup_table_column();
+        let q_running = meta.selector();
+        let q_limb = meta.complex_selector();
+        let q_top = meta.complex_selector();
+
+        meta.create_gate("running sum", |meta| {
+            let s = meta.query_selector(q_running);
+            let limb = meta.query_advice(limb, Rotation::cur());
+            let z_prev = meta.query_advice(z, Rotation::prev());
+            let z_cur = meta.query_advice(z, Rotation::cur());
+            let radix = Expression::Constant(F::from(1u64 << LIMB_BITS));
+            vec![s * (z_cur - (z_prev * radix + limb))]
+        });
+
+        // out of the enabled rows, the inputs are 0, which is in the table
+        meta.lookup("limb", |meta| {
+            let s = meta.query_selector(q_limb);
+            let limb = meta.query_advice(limb, Rotation::cur());
+            vec![(s * limb, table)]
+        });
+
+        meta.lookup("short top limb", |meta| {
+            let s = meta.query_selector(q_top);
+            let limb = meta.query_advice(limb, Rotation::cur());
+            let shift = meta.query_fixed(shift, Rotation::cur());
+            vec![(s * limb * shift, table)]
+        });
+
+        RangeCheckConfig {
+            limb,
+            z,
+            shift,
+            table,
+            q_running,
+            q_limb,
+            q_top,
+        }
+    }
+
+    pub fn load_table(&self, mut layouter: impl Layouter<F>) -> Result<(), ErrorFront> {
+        layouter.assign_table(
+            || "limb table",
+            |mut table| {
+                for v in 0..(1 << LIMB_BITS) {
+                    table.assign_cell(
+                        || "limb",
+                        self.config.table,
+                        v,
+                        || Value::known(F::from(v as u64)),
+                    )?;
+                }
+                Ok(())
+            },
+        )
+    }
+
+    /// Constrains `value` to be less than `2^bits`.
+    ///
+    /// `bits` must stay below the size of the field, e.g. at most 253 bits on bn256,
+    /// otherwise the running sum wraps around the modulus and proves nothing.
+    pub fn range_check(
+        &self,
+        mut layouter: impl Layouter<F>,
+        value: &AssignedCell<F, F>,
+        bits: usize,
+    ) -> Result<(), ErrorFront> {
+        if bits == 0 || bits >= F::NUM_BITS as usize {
+            return Err(ErrorFront::Synthesis);
+        }
+        let config = &self.config;
+        let num_limbs = bits.div_ceil(LIMB_BITS);
+        let top_bits = bits - (num_limbs - 1) * LIMB_BITS;
+
+        // little endian limbs, from the little endian repr
+        let limbs: Value<Vec<F>> = value.value().map(|v| {
+            let repr = v.to_repr();
+            let bit = |i: usize| (repr.as_ref()[i / 8] >> (i % 8)) & 1;
+            (0..num_limbs)
+                .map(|l| {
+                    let limb = (0..LIMB_BITS)
+                        .filter(|j| l * LIMB_BITS + j < 8 * repr.as_ref().len())
+                        .fold(0u64, |acc, j| acc | (bit(l * LIMB_BITS + j) as u64) << j);
+                    F::from(limb)
+                })
+                .collect()
+        });
+
+        layouter.assign_region(
+            || "range check",
+            |mut region| {
+                let mut z = region.assign_advice_from_constant(|| "z", config.z, 0, F::ZERO)?;
+
+                for row in 1..=num_limbs {
+                    let index = num_limbs - row;
+                    config.q_running.enable(&mut region, row)?;
+                    config.q_limb.enable(&mut region, row)?;
+                    if row == 1 && top_bits < LIMB_BITS {
+                        config.q_top.enable(&mut region, row)?;
+                        region.assign_fixed(
+                            || "shift",
+                            config.shift,
+                            row,
+                            || Value::known(F::from(1u64 << (LIMB_BITS - top_bits))),
+                        )?;
+                    }
+
+                    let limb = limbs.as_ref().map(|limbs| limbs[index]);
+                    region.assign_advice(|| "limb", config.limb, row, || limb)?;
+                    let z_value =
+                        z.value().copied() * Value::known(F::from(1u64 << LIMB_BITS)) + limb;
+                    z = region.assign_advice(|| "z", config.z, row, || z_value)?;
+                }
+
+                // the limbs add up to the value we check
+                region.constrain_equal(value.cell(), z.cell())
+            },
+        )
+    }
+}
@@ -0,0 +1,220 @@
+use halo2_proofs::{
+    circuit::{AssignedCell, Layouter, Value},
+    halo2curves::ff::PrimeField,
+    plonk::{Advice, Column, ConstraintSystem, ErrorFront, Expression, Fixed, Selector},
+    poly::Rotation,
+};
+use serde::Deserialize;
+use std::marker::PhantomData;
+
+/// Which of the two roots `r` and `-r` is the one we accept.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+pub enum CanonicalRule {
+    /// The root that is at most `(p - 1) / 2`.
+    #[default]
+    Smaller,
+    /// The root whose least significant bit is 0. As `p` is odd, `r` and `p - r` have different parities.
+    EvenLsb,
+    /// The root that fits in this many bits. Only picks one root when `2^bits <= p / 2`,
+    /// e.g. 64 for a `u64` root.
+    BitLength(usize),
+}
+
+impl CanonicalRule {
+    /// The largest value the decomposition of the root may hold.
+    fn bound<F: PrimeField>(&self) -> F {
+        match self {
+            // -(1/2) == (p - 1) / 2
+            CanonicalRule::Smaller => -F::TWO_INV,
+            // every canonical representation is at most p - 1
+            CanonicalRule::EvenLsb | CanonicalRule::BitLength(_) => -F::ONE,
+        }
+    }
+}
+
+/// Bit `i` of the canonical representation of `v`, `to_repr` is little endian
+/// for the bn256 and pasta fields.
+fn bit<F: PrimeField>(v: &F, i: usize) -> bool {
+    (v.to_repr().as_ref()[i / 8] >> (i % 8)) & 1 == 1
+}
+
+/// Columns of the decomposition region, one row per bit of the root, most significant first:
+/// - `bit`: the bit
+/// - `acc`: the bits so far as an integer, the last row is the root
+/// - `eq`, `lt`: whether the bits so far are equal to, or less than, those of `bound`
+/// - `bound`: the bits of the largest accepted root
+///
+/// The region starts with a row of initial values (0, 1, 0) for `acc`, `eq` and `lt`.
+/// The square region reuses `bit` and `acc` for the root and its square.
+#[derive(Clone, Debug)]
+pub struct SquareRootConfig {
+    bit: Column<Advice>,
+    acc: Column<Advice>,
+    eq: Column<Advice>,
+    lt: Column<Advice>,
+    bound: Column<Fixed>,
+    q_square: Selector,
+    q_bit: Selector,
+    q_last: Selector,
+    q_zero: Selector,
+}
+
+#[derive(Clone, Debug)]
+pub struct SquareRootChip<F: PrimeField> {
+    config: SquareRootConfig,
+    rule: CanonicalRule,
+    _marker: PhantomData<F>,
+}
+
+impl<F: PrimeField> SquareRootChip<F> {
+    pub fn construct(config: SquareRootConfig, rule: CanonicalRule) -> Self {
+        Self {
+            config,
+            rule,
+            _marker: PhantomData,
+        }
+    }
+
+    /// The circuit must also enable a constant column, the initial row of the
+    /// decomposition comes from `assign_advice_from_constant`.
+    pub fn configure(
+        meta: &mut ConstraintSystem<F>,
+        advice: [Column<Advice>; 4],
+        bound: Column<Fixed>,
+    ) -> SquareRootConfig {
+        let [bit, acc, eq, lt] = advice;
+        for column in advice {
+            meta.enable_equality(column);
+        }
+
+        let q_square = meta.selector();
+        let q_bit = meta.selector();
+        let q_last = meta.selector();
+        let q_zero = meta.selector();
+
+        meta.create_gate("square", |meta| {
+            let s = meta.query_selector(q_square);
+            let root = meta.query_advice(bit, Rotation::cur());
+            let square = meta.query_advice(acc, Rotation::cur());
+            vec![s * (root.clone() * root - square)]
+        });
+
+        meta.create_gate("decompose", |meta| {
+            let s = meta.query_selector(q_bit);
+            let one = Expression::Constant(F::ONE);
+            let two = Expression::Constant(F::from(2));
+
+            let b = meta.query_advice(bit, Rotation::cur());
+            let c = meta.query_fixed(bound, Rotation::cur());
+            let acc_prev = meta.query_advice(acc, Rotation::prev());
+            let acc_cur = meta.query_advice(acc, Rotation::cur());
+            let eq_prev = meta.query_advice(eq, Rotation::prev());
+            let eq_cur = meta.query_advice(eq, Rotation::cur());
+            let lt_prev = meta.query_advice(lt, Rotation::prev());
+            let lt_cur = meta.query_advice(lt, Rotation::cur());
+
+            let xor = b.clone() + c.clone() - two.clone() * b.clone() * c.clone();
+            vec![
+                s.clone() * b.clone() * (one.clone() - b.clone()),
+                s.clone() * (acc_cur - (two * acc_prev + b.clone())),
+                s.clone() * (eq_cur - eq_prev.clone() * (one.clone() - xor)),
+                s * (lt_cur - (lt_prev + eq_prev * (one - b) * c)),
+            ]
+        });
+
+        // the root is at most the bound: smaller, or equal all the way
+        meta.create_gate("below bound", |meta| {
+            let s = meta.query_selector(q_last);
+            let eq = meta.query_advice(eq, Rotation::cur());
+            let lt = meta.query_advice(lt, Rotation::cur());
+            vec![s * (eq + lt - Expression::Constant(F::ONE))]
+        });
+
+        // even LSB and bit length rules
+        meta.create_gate("zero bit", |meta| {
+            let s = meta.query_selector(q_zero);
+            let b = meta.query_advice(bit, Rotation::cur());
+            vec![s * b]
+        });
+
+        SquareRootConfig {
+            bit,
+            acc,
+            eq,
+            lt,
+            bound,
+            q_square,
+            q_bit,
+            q_last,
+            q_zero,
+        }
+    }
+
+    /// Assigns `root` and its square, and checks `root` is the canonical root.
+    /// Returns the square, for the caller to expose.
+    pub fn assign(
+        &self,
+        mut layouter: impl Layouter<F>,
+        root: Value<F>,
+    ) -> Result<AssignedCell<F, F>, ErrorFront> {
+        let config = &self.config;
+        let num_bits = F::NUM_BITS as usize;
+        let bound = self.rule.bound::<F>();
+
+        let (root_cell, square) = layouter.assign_region(
+            || "square",
+            |mut region| {
+                config.q_square.enable(&mut region, 0)?;
+                let root_cell = region.assign_advice(|| "root", config.bit, 0, || root)?;
+                let square = region.assign_advice(|| "square", config.acc, 0, || root * root)?;
+                Ok((root_cell, square))
+            },
+        )?;
+
+        layouter.assign_region(
+            || "decompose root",
+            |mut region| {
+                let mut acc =
+                    region.assign_advice_from_constant(|| "acc", config.acc, 0, F::ZERO)?;
+                let mut eq = region.assign_advice_from_constant(|| "eq", config.eq, 0, F::ONE)?;
+                let mut lt = region.assign_advice_from_constant(|| "lt", config.lt, 0, F::ZERO)?;
+
+                for (row, i) in (0..num_bits).rev().enumerate().map(|(r, i)| (r + 1, i)) {
+                    config.q_bit.enable(&mut region, row)?;
+                    match self.rule {
+                        CanonicalRule::EvenLsb if i == 0 => {
+                            config.q_zero.enable(&mut region, row)?
+                        }
+                        CanonicalRule::BitLength(bits) if i >= bits => {
+                            config.q_zero.enable(&mut region, row)?
+                        }
+                        _ => {}
+                    }
+
+                    let c = bit(&bound, i);
+                    let c_f = F::from(c as u64);
+                    region.assign_fixed(|| "bound", config.bound, row, || Value::known(c_f))?;
+
+                    let b = root.map(|r| F::from(bit(&r, i) as u64));
+                    region.assign_advice(|| "bit", config.bit, row, || b)?;
+
+                    let acc_value = acc.value().copied() * Value::known(F::from(2)) + b;
+                    let xor = b.map(|b| if b == c_f { F::ZERO } else { F::ONE });
+                    let eq_value = eq.value().copied() * (Value::known(F::ONE) - xor);
+                    let lt_value = lt.value().copied()
+                        + eq.value().copied() * (Value::known(F::ONE) - b) * Value::known(c_f);
+
+                    acc = region.assign_advice(|| "acc", config.acc, row, || acc_value)?;
+                    eq = region.assign_advice(|| "eq", config.eq, row, || eq_value)?;
+                    lt = region.assign_advice(|| "lt", config.lt, row, || lt_value)?;
+                }
+                config.q_last.enable(&mut region, num_bits)?;
+
+                // the bits are those of the root we squared
+                region.constrain_equal(root_cell.cell(), acc.cell())
+            },
+        )?;
+
+        Ok(square)
+    }
+}
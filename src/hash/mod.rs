@@ -1,20 +1,283 @@
 use halo2_poseidon::poseidon::primitives::{ConstantLength, Hash, P128Pow5T3 as OrchardNullifier};
-use halo2_proofs::{circuit::Value, dev::MockProver, halo2curves::pasta::pallas::Base as PallasFp};
+use halo2_proofs::{
+    circuit::Value,
+    halo2curves::{bn256::Fr, pasta::pallas::Base as PallasFp},
+};
 use std::marker::PhantomData;
 
-mod poseidon;
-use poseidon::PoseidonCircuit;
+use crate::scenario::{CircuitScenario, Registry, Verdict};
+
+pub mod poseidon;
+pub mod poseidon_var;
+pub mod spec;
+pub mod sponge;
+use poseidon::{Output, PoseidonCircuit, Private, Public, PublicWithMessage};
+use poseidon_var::{hash_padded, hash_var_len, PoseidonVarLenCircuit};
+use spec::{P128Pow5T3Bn256, P128Pow5T5Bn256};
+use sponge::{NativeSponge, SpongeCircuit, SpongeOp};
+
+pub fn register(registry: &mut Registry) {
+    fn circuit<O: Output>(
+        message: [PallasFp; 3],
+    ) -> PoseidonCircuit<PallasFp, OrchardNullifier, 3, 2, 3, O> {
+        let output = Hash::<_, OrchardNullifier, ConstantLength<3>, 3, 2>::init().hash(message);
+        PoseidonCircuit {
+            message: Value::known(message),
+            output: Value::known(output),
+            _spec: PhantomData,
+        }
+    }
 
-pub fn hash_circuit() {
     let message = [PallasFp::from(1), PallasFp::from(2), PallasFp::from(3)];
     let output = Hash::<_, OrchardNullifier, ConstantLength<3>, 3, 2>::init().hash(message);
 
-    let circuit = PoseidonCircuit::<OrchardNullifier, 3, 2, 3> {
-        message: Value::known(message),
+    registry.register(CircuitScenario::new(
+        "hash/poseidon",
+        circuit::<Private>(message),
+        7,
+        vec![vec![]],
+        Verdict::Accept,
+    ));
+
+    // the digest the verifier cares about is `output`, but it never sees it:
+    // a proof for any other message is just as good
+    let other = [PallasFp::from(4), PallasFp::from(5), PallasFp::from(6)];
+    registry.register(CircuitScenario::new(
+        "hash/poseidon-private-output-any-message",
+        circuit::<Private>(other),
+        7,
+        vec![vec![]],
+        Verdict::Accept,
+    ));
+
+    registry.register(CircuitScenario::new(
+        "hash/poseidon-public-output",
+        circuit::<Public>(message),
+        7,
+        vec![vec![output]],
+        Verdict::Accept,
+    ));
+    registry.register(CircuitScenario::new(
+        "hash/poseidon-public-output-other-message",
+        circuit::<Public>(other),
+        7,
+        vec![vec![output]],
+        Verdict::Reject,
+    ));
+
+    let public =
+        |message: [PallasFp; 3]| std::iter::once(output).chain(message).collect::<Vec<_>>();
+    registry.register(CircuitScenario::new(
+        "hash/poseidon-public-message",
+        circuit::<PublicWithMessage>(message),
+        7,
+        vec![public(message)],
+        Verdict::Accept,
+    ));
+    // the right digest, but not the message it comes from
+    registry.register(CircuitScenario::new(
+        "hash/poseidon-public-message-other-message",
+        circuit::<PublicWithMessage>(other),
+        7,
+        vec![public(other)],
+        Verdict::Reject,
+    ));
+
+    poseidon_bn256(registry);
+    poseidon_var_len(registry);
+    sponge(registry);
+}
+
+/// The same circuit over bn256, for the KZG verifiers
+fn poseidon_bn256(registry: &mut Registry) {
+    let message = [Fr::from(1), Fr::from(2), Fr::from(3)];
+    let output = Hash::<_, P128Pow5T3Bn256, ConstantLength<3>, 3, 2>::init().hash(message);
+    registry.register(CircuitScenario::new(
+        "hash/poseidon-bn256",
+        PoseidonCircuit::<Fr, P128Pow5T3Bn256, 3, 2, 3> {
+            message: Value::known(message),
+            output: Value::known(output),
+            _spec: PhantomData,
+        },
+        7,
+        vec![vec![]],
+        Verdict::Accept,
+    ));
+
+    // a message of 4 is absorbed in one permutation with a rate of 4
+    let message = [Fr::from(1), Fr::from(2), Fr::from(3), Fr::from(4)];
+    let output = Hash::<_, P128Pow5T5Bn256, ConstantLength<4>, 5, 4>::init().hash(message);
+    registry.register(CircuitScenario::new(
+        "hash/poseidon-bn256-width5",
+        PoseidonCircuit::<Fr, P128Pow5T5Bn256, 5, 4, 4> {
+            message: Value::known(message),
+            output: Value::known(output),
+            _spec: PhantomData,
+        },
+        7,
+        vec![vec![]],
+        Verdict::Accept,
+    ));
+}
+
+/// Messages of up to 4 words
+type VarLen = PoseidonVarLenCircuit<PallasFp, OrchardNullifier, 3, 2, 4>;
+
+fn poseidon_var_len(registry: &mut Registry) {
+    let hash =
+        |message: &[PallasFp]| hash_var_len::<_, OrchardNullifier, 3, 2, 4>(message).unwrap();
+    let circuit = |message: &[PallasFp], output: PallasFp| VarLen {
+        message: message.iter().copied().map(Value::known).collect(),
         output: Value::known(output),
+        length: None,
+        _spec: PhantomData,
+    };
+
+    let message = [PallasFp::from(1), PallasFp::from(2), PallasFp::from(3)];
+    registry.register(CircuitScenario::new(
+        "hash/poseidon-var-len",
+        circuit(&message, hash(&message)),
+        8,
+        vec![vec![hash(&message)]],
+        Verdict::Accept,
+    ));
+    registry.register(CircuitScenario::new(
+        "hash/poseidon-var-len-full",
+        circuit(&[message[0]; 4], hash(&[message[0]; 4])),
+        8,
+        vec![vec![hash(&[message[0]; 4])]],
+        Verdict::Accept,
+    ));
+
+    // `merkle_nohash1` style: drop the last word by turning its flag off, with the length
+    // and the digest of a message of 2 words. The word isn't 0, so it doesn't pass
+    let padded = [message[0], message[1], message[2], PallasFp::from(0)];
+    let digest = hash_padded::<_, OrchardNullifier, 3, 2, 4>(padded, 2);
+    let truncated = VarLen {
+        length: Some(2),
+        ..circuit(&message, digest)
+    };
+    registry.register(CircuitScenario::new(
+        "hash/poseidon-var-len-truncated",
+        truncated,
+        8,
+        vec![vec![digest]],
+        Verdict::Reject,
+    ));
+
+    // with a 0 instead, it is the proof of `[1, 2]`: the padding is the only way to collide
+    let digest = hash(&message[..2]);
+    let zero_padded = VarLen {
+        length: Some(2),
+        ..circuit(&[message[0], message[1], PallasFp::from(0)], digest)
+    };
+    registry.register(CircuitScenario::new(
+        "hash/poseidon-var-len-zero-padded",
+        zero_padded,
+        8,
+        vec![vec![digest]],
+        Verdict::Accept,
+    ));
+
+    let message = [Fr::from(1), Fr::from(2), Fr::from(3)];
+    let output = hash_var_len::<_, P128Pow5T3Bn256, 3, 2, 4>(&message).unwrap();
+    registry.register(CircuitScenario::new(
+        "hash/poseidon-var-len-bn256",
+        PoseidonVarLenCircuit::<Fr, P128Pow5T3Bn256, 3, 2, 4> {
+            message: message.iter().copied().map(Value::known).collect(),
+            output: Value::known(output),
+            length: None,
+            _spec: PhantomData,
+        },
+        8,
+        vec![vec![output]],
+        Verdict::Accept,
+    ));
+}
+
+fn sponge(registry: &mut Registry) {
+    type Native = NativeSponge<PallasFp, OrchardNullifier, 3, 2>;
+    let message = [PallasFp::from(1), PallasFp::from(2), PallasFp::from(3)];
+
+    // absorb 3 and squeeze 1 with the capacity of `ConstantLength<3>`: `primitives::Hash`
+    let capacity = 3u128 << 64;
+    let output = Hash::<_, OrchardNullifier, ConstantLength<3>, 3, 2>::init().hash(message);
+    let mut ops = message
+        .iter()
+        .map(|m| SpongeOp::Absorb(Value::known(*m)))
+        .collect::<Vec<_>>();
+    ops.push(SpongeOp::Squeeze(Value::known(output)));
+    registry.register(CircuitScenario::new(
+        "hash/sponge-constant-length",
+        SpongeCircuit::<PallasFp, OrchardNullifier, 3, 2> {
+            capacity,
+            ops,
+            _spec: PhantomData,
+        },
+        8,
+        vec![],
+        Verdict::Accept,
+    ));
+
+    // absorb 2, squeeze 3 (one more permutation), absorb 1, squeeze 1
+    let mut native = Native::new(PallasFp::from(0));
+    let mut ops = vec![];
+    let absorb = |native: &mut Native, m: PallasFp| {
+        native.absorb(m);
+        SpongeOp::Absorb(Value::known(m))
+    };
+    ops.push(absorb(&mut native, message[0]));
+    ops.push(absorb(&mut native, message[1]));
+    let squeezed = [native.squeeze(), native.squeeze(), native.squeeze()];
+    ops.extend(squeezed.map(|s| SpongeOp::Squeeze(Value::known(s))));
+    ops.push(absorb(&mut native, message[2]));
+    let last = native.squeeze();
+    ops.push(SpongeOp::Squeeze(Value::known(last)));
+
+    let circuit = SpongeCircuit::<PallasFp, OrchardNullifier, 3, 2> {
+        capacity: 0,
+        ops: ops.clone(),
+        _spec: PhantomData,
+    };
+    registry.register(CircuitScenario::new(
+        "hash/sponge-interleaved",
+        circuit,
+        9,
+        vec![],
+        Verdict::Accept,
+    ));
+
+    // the third squeeze needs another permutation, the second element of the rate won't do
+    ops[4] = SpongeOp::Squeeze(Value::known(squeezed[1]));
+    let circuit = SpongeCircuit::<PallasFp, OrchardNullifier, 3, 2> {
+        capacity: 0,
+        ops,
         _spec: PhantomData,
     };
+    registry.register(CircuitScenario::new(
+        "hash/sponge-stale-output",
+        circuit,
+        9,
+        vec![],
+        Verdict::Reject,
+    ));
 
-    let prover = MockProver::run(7, &circuit, vec![]).unwrap();
-    assert!(prover.verify().is_ok());
+    let message = [Fr::from(1), Fr::from(2), Fr::from(3)];
+    let output = Hash::<_, P128Pow5T3Bn256, ConstantLength<3>, 3, 2>::init().hash(message);
+    let mut ops = message
+        .iter()
+        .map(|m| SpongeOp::Absorb(Value::known(*m)))
+        .collect::<Vec<_>>();
+    ops.push(SpongeOp::Squeeze(Value::known(output)));
+    registry.register(CircuitScenario::new(
+        "hash/sponge-bn256",
+        SpongeCircuit::<Fr, P128Pow5T3Bn256, 3, 2> {
+            capacity,
+            ops,
+            _spec: PhantomData,
+        },
+        8,
+        vec![],
+        Verdict::Accept,
+    ));
 }
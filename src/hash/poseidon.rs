@@ -4,27 +4,82 @@ use halo2_poseidon::poseidon::{
 };
 use halo2_proofs::{
     circuit::{Layouter, SimpleFloorPlanner, Value},
-    halo2curves::pasta::Fp,
-    plonk::{Circuit, ConstraintSystem, ErrorFront},
+    halo2curves::ff::PrimeField,
+    plonk::{Circuit, Column, ConstraintSystem, ErrorFront, Instance},
 };
-use std::marker::PhantomData;
+use serde::Deserialize;
+use std::{fmt, marker::PhantomData};
 
+/// What the verifier sees of the hash.
+///
+/// It decides which instance rows the circuit constrains, so it is part of the type
+/// and not of the witness: an input file can't change the circuit under the keys.
+pub trait Output: fmt::Debug + Default + Clone + Copy {
+    /// The digest is the first instance row.
+    const DIGEST: bool;
+    /// The `L` words of the message are in the next instance rows.
+    const MESSAGE: bool;
+}
+
+/// Nothing: `output` is compared with the digest in the witness only,
+/// so the proof says "I know some message and its hash", which anyone can prove.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Private;
+
+/// The digest is the first instance row: "I know a preimage of this hash".
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Public;
+
+/// The digest, then the `L` words of the message in the next instance rows.
 #[derive(Debug, Default, Clone, Copy)]
+pub struct PublicWithMessage;
+
+impl Output for Private {
+    const DIGEST: bool = false;
+    const MESSAGE: bool = false;
+}
+
+impl Output for Public {
+    const DIGEST: bool = true;
+    const MESSAGE: bool = false;
+}
+
+impl Output for PublicWithMessage {
+    const DIGEST: bool = true;
+    const MESSAGE: bool = true;
+}
+
+#[derive(Debug, Clone)]
+pub struct PoseidonConfig<F: PrimeField, const WIDTH: usize, const RATE: usize> {
+    pub pow5config: Pow5Config<F, WIDTH, RATE>,
+    pub instance: Column<Instance>,
+}
+
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
 pub struct PoseidonCircuit<
-    S: Spec<Fp, WIDTH, RATE>,
+    F: PrimeField,
+    S: Spec<F, WIDTH, RATE>,
     const WIDTH: usize,
     const RATE: usize,
     const L: usize,
+    O: Output = Private,
 > {
-    pub message: Value<[Fp; L]>,
-    pub output: Value<Fp>,
-    pub _spec: PhantomData<S>,
+    #[serde(default, deserialize_with = "crate::inputs::value_array")]
+    pub message: Value<[F; L]>,
+    #[serde(default, deserialize_with = "crate::inputs::value")]
+    pub output: Value<F>,
+    #[serde(skip)]
+    pub _spec: PhantomData<(S, O)>,
 }
 
-impl<S: Spec<Fp, WIDTH, RATE>, const WIDTH: usize, const RATE: usize, const L: usize> Circuit<Fp>
-    for PoseidonCircuit<S, WIDTH, RATE, L>
+impl<F, S, const WIDTH: usize, const RATE: usize, const L: usize, O> Circuit<F>
+    for PoseidonCircuit<F, S, WIDTH, RATE, L, O>
+where
+    F: PrimeField,
+    S: Spec<F, WIDTH, RATE>,
+    O: Output,
 {
-    type Config = Pow5Config<Fp, WIDTH, RATE>;
+    type Config = PoseidonConfig<F, WIDTH, RATE>;
     type FloorPlanner = SimpleFloorPlanner;
 
     fn without_witnesses(&self) -> Self {
@@ -35,7 +90,7 @@ impl<S: Spec<Fp, WIDTH, RATE>, const WIDTH: usize, const RATE: usize, const L: u
         }
     }
 
-    fn configure(meta: &mut ConstraintSystem<Fp>) -> Pow5Config<Fp, WIDTH, RATE> {
+    fn configure(meta: &mut ConstraintSystem<F>) -> PoseidonConfig<F, WIDTH, RATE> {
         let state = (0..WIDTH).map(|_| meta.advice_column()).collect::<Vec<_>>();
         let partial_sbox = meta.advice_column();
 
@@ -44,21 +99,27 @@ impl<S: Spec<Fp, WIDTH, RATE>, const WIDTH: usize, const RATE: usize, const L: u
 
         meta.enable_constant(rc_b[0]);
 
-        Pow5Chip::configure::<S>(
-            meta,
-            state.try_into().unwrap(),
-            partial_sbox,
-            rc_a.try_into().unwrap(),
-            rc_b.try_into().unwrap(),
-        )
+        let instance = meta.instance_column();
+        meta.enable_equality(instance);
+
+        PoseidonConfig {
+            pow5config: Pow5Chip::configure::<S>(
+                meta,
+                state.try_into().unwrap(),
+                partial_sbox,
+                rc_a.try_into().unwrap(),
+                rc_b.try_into().unwrap(),
+            ),
+            instance,
+        }
     }
 
     fn synthesize(
         &self,
-        config: Pow5Config<Fp, WIDTH, RATE>,
-        mut layouter: impl Layouter<Fp>,
+        config: PoseidonConfig<F, WIDTH, RATE>,
+        mut layouter: impl Layouter<F>,
     ) -> Result<(), ErrorFront> {
-        let chip = Pow5Chip::construct(config.clone());
+        let chip = Pow5Chip::construct(config.pow5config.clone());
 
         let message = layouter.assign_region(
             || "load message",
@@ -67,7 +128,7 @@ impl<S: Spec<Fp, WIDTH, RATE>, const WIDTH: usize, const RATE: usize, const L: u
                     let value = self.message.map(|message_vals| message_vals[i]);
                     region.assign_advice(
                         || format!("load message_{}", i),
-                        config.state[i],
+                        config.pow5config.state[i],
                         0,
                         || value,
                     )
@@ -77,20 +138,98 @@ impl<S: Spec<Fp, WIDTH, RATE>, const WIDTH: usize, const RATE: usize, const L: u
                 Ok(message?.try_into().unwrap())
             },
         )?;
+        if O::MESSAGE {
+            for (i, word) in message.iter().enumerate() {
+                layouter.constrain_instance(word.cell(), config.instance, 1 + i)?;
+            }
+        }
 
         let hasher = Hash::<_, _, S, ConstantLength<L>, WIDTH, RATE>::init(
             chip,
             layouter.namespace(|| "init"),
         )?;
         let output = hasher.hash(layouter.namespace(|| "hash"), message)?;
+        if O::DIGEST {
+            layouter.constrain_instance(output.cell(), config.instance, 0)?;
+        }
 
         layouter.assign_region(
             || "constrain output",
             |mut region| {
-                let expected_var =
-                    region.assign_advice(|| "load output", config.state[0], 0, || self.output)?;
+                let expected_var = region.assign_advice(
+                    || "load output",
+                    config.pow5config.state[0],
+                    0,
+                    || self.output,
+                )?;
                 region.constrain_equal(output.cell(), expected_var.cell())
             },
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use halo2_poseidon::poseidon::primitives::{
+        Hash as NativeHash, P128Pow5T3 as OrchardNullifier,
+    };
+    use halo2_proofs::halo2curves::pasta::Fp;
+    use rand::rngs::OsRng;
+
+    use super::*;
+    use crate::{
+        prove::{prove, Backend, Proof},
+        scenario::Verdict,
+        tamper::Tamper,
+    };
+
+    const K: u32 = 7;
+
+    fn digest(message: [u64; 3]) -> Fp {
+        NativeHash::<_, OrchardNullifier, ConstantLength<3>, 3, 2>::init()
+            .hash(message.map(Fp::from))
+    }
+
+    fn circuit<O: Output>(message: [u64; 3]) -> PoseidonCircuit<Fp, OrchardNullifier, 3, 2, 3, O> {
+        PoseidonCircuit {
+            message: Value::known(message.map(Fp::from)),
+            output: Value::known(digest(message)),
+            _spec: PhantomData,
+        }
+    }
+
+    /// Proves `a` and `b`, and checks both proofs with the key and the instances of `b`.
+    fn swap<O: Output>(
+        a: [u64; 3],
+        b: [u64; 3],
+        instances: impl Fn([u64; 3]) -> Vec<Vec<Fp>>,
+    ) -> [Verdict; 2] {
+        let params = Fp::setup(K, OsRng);
+        let proof = |message| {
+            let circuit = circuit::<O>(message);
+            prove(&params, K, &circuit, instances(message), &Tamper::new())
+                .unwrap()
+                .1
+        };
+        let (proof_a, proof_b) = (proof(a), proof(b));
+
+        let a_as_b = Proof {
+            vk: proof_b.vk.clone(),
+            instances: proof_b.instances.clone(),
+            ..proof_a
+        };
+        [a_as_b.verify(&params), proof_b.verify(&params)]
+    }
+
+    #[test]
+    fn private_output_proves_any_message() {
+        let verdicts = swap::<Private>([1, 2, 3], [4, 5, 6], |_| vec![vec![]]);
+        assert_eq!(verdicts, [Verdict::Accept, Verdict::Accept]);
+    }
+
+    #[test]
+    fn public_output_binds_the_message() {
+        let verdicts = swap::<Public>([1, 2, 3], [4, 5, 6], |m| vec![vec![digest(m)]]);
+        assert_eq!(verdicts, [Verdict::Reject, Verdict::Accept]);
+    }
+}
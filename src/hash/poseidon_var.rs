@@ -0,0 +1,352 @@
+use halo2_poseidon::poseidon::{
+    primitives::{self as poseidon, ConstantLength, Spec},
+    Hash, Pow5Chip, Pow5Config,
+};
+use halo2_proofs::{
+    circuit::{AssignedCell, Layouter, SimpleFloorPlanner, Value},
+    halo2curves::ff::PrimeField,
+    plonk::{
+        Advice, Circuit, Column, ConstraintSystem, ErrorFront, Expression, Instance, Selector,
+    },
+    poly::Rotation,
+};
+use serde::Deserialize;
+use std::marker::PhantomData;
+
+/// Columns of the message region, one row per word up to `MAX`:
+/// - `word`: the message, then zeros
+/// - `flag`: 1 for the words of the message, then 0
+/// - `count`: the flags so far, the last row is the length
+///
+/// The digest is the first row of `instance`.
+#[derive(Clone, Debug)]
+pub struct VarLenConfig<F: PrimeField, const WIDTH: usize, const RATE: usize> {
+    pow5config: Pow5Config<F, WIDTH, RATE>,
+    word: Column<Advice>,
+    flag: Column<Advice>,
+    count: Column<Advice>,
+    instance: Column<Instance>,
+    q_word: Selector,
+    q_first: Selector,
+    q_next: Selector,
+}
+
+/// Hashes a message of any length up to `MAX`: `Poseidon([Poseidon(padded message), length])`.
+///
+/// The message is padded with zeros to `MAX` words, so `[1, 2]` and `[1, 2, 0]` are the
+/// same padded message, the length tells them apart. The flags can only turn off once,
+/// and the words after must be 0: the prover can't drop words of the message, nor hash
+/// more than it says.
+///
+/// The digest is the public input: with a private one, the proof would only say
+/// "I know some message and its hash", like `poseidon::Private`.
+///
+/// `length` is the witness of the flags, the words of `message` past it are padding:
+/// `[1, 2, 0]` of length 2 is the message `[1, 2]`, `[1, 2, 3]` of length 2 is no message.
+#[derive(Debug, Default, Clone, Deserialize)]
+pub struct PoseidonVarLenCircuit<
+    F: PrimeField,
+    S: Spec<F, WIDTH, RATE>,
+    const WIDTH: usize,
+    const RATE: usize,
+    const MAX: usize,
+> {
+    #[serde(default, deserialize_with = "crate::inputs::values")]
+    pub message: Vec<Value<F>>,
+    #[serde(default, deserialize_with = "crate::inputs::value")]
+    pub output: Value<F>,
+    /// How many words of `message` are flagged, all of them when `None`.
+    #[serde(default)]
+    pub length: Option<usize>,
+    #[serde(skip)]
+    pub _spec: PhantomData<S>,
+}
+
+impl<F, S, const WIDTH: usize, const RATE: usize, const MAX: usize> Circuit<F>
+    for PoseidonVarLenCircuit<F, S, WIDTH, RATE, MAX>
+where
+    F: PrimeField,
+    S: Spec<F, WIDTH, RATE>,
+{
+    type Config = VarLenConfig<F, WIDTH, RATE>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self {
+            message: vec![Value::unknown(); self.message.len()],
+            output: Value::unknown(),
+            length: self.length,
+            _spec: PhantomData,
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        let state = (0..WIDTH).map(|_| meta.advice_column()).collect::<Vec<_>>();
+        let partial_sbox = meta.advice_column();
+
+        let rc_a = (0..WIDTH).map(|_| meta.fixed_column()).collect::<Vec<_>>();
+        let rc_b = (0..WIDTH).map(|_| meta.fixed_column()).collect::<Vec<_>>();
+
+        meta.enable_constant(rc_b[0]);
+
+        let pow5config = Pow5Chip::configure::<S>(
+            meta,
+            state.try_into().unwrap(),
+            partial_sbox,
+            rc_a.try_into().unwrap(),
+            rc_b.try_into().unwrap(),
+        );
+
+        let word = meta.advice_column();
+        let flag = meta.advice_column();
+        let count = meta.advice_column();
+        meta.enable_equality(word);
+        meta.enable_equality(count);
+        let instance = meta.instance_column();
+        meta.enable_equality(instance);
+
+        let q_word = meta.selector();
+        let q_first = meta.selector();
+        let q_next = meta.selector();
+
+        meta.create_gate("padded word", |meta| {
+            let s = meta.query_selector(q_word);
+            let one = Expression::Constant(F::ONE);
+            let word = meta.query_advice(word, Rotation::cur());
+            let flag = meta.query_advice(flag, Rotation::cur());
+            vec![
+                s.clone() * flag.clone() * (one.clone() - flag.clone()),
+                // out of the message, the word is 0
+                s * word * (one - flag),
+            ]
+        });
+
+        meta.create_gate("first length", |meta| {
+            let s = meta.query_selector(q_first);
+            let flag = meta.query_advice(flag, Rotation::cur());
+            let count = meta.query_advice(count, Rotation::cur());
+            vec![s * (count - flag)]
+        });
+
+        meta.create_gate("length", |meta| {
+            let s = meta.query_selector(q_next);
+            let one = Expression::Constant(F::ONE);
+            let flag_prev = meta.query_advice(flag, Rotation::prev());
+            let flag_cur = meta.query_advice(flag, Rotation::cur());
+            let count_prev = meta.query_advice(count, Rotation::prev());
+            let count_cur = meta.query_advice(count, Rotation::cur());
+            vec![
+                // once off, the flag stays off
+                s.clone() * flag_cur.clone() * (one - flag_prev),
+                s * (count_cur - (count_prev + flag_cur)),
+            ]
+        });
+
+        VarLenConfig {
+            pow5config,
+            word,
+            flag,
+            count,
+            instance,
+            q_word,
+            q_first,
+            q_next,
+        }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), ErrorFront> {
+        let length = self.length.unwrap_or(self.message.len());
+        if MAX == 0 || self.message.len() > MAX || length > self.message.len() {
+            return Err(ErrorFront::Synthesis);
+        }
+        let chip = Pow5Chip::construct(config.pow5config.clone());
+
+        let (words, length) = layouter.assign_region(
+            || "load message",
+            |mut region| {
+                let mut words = vec![];
+                let mut count: Option<AssignedCell<F, F>> = None;
+                for row in 0..MAX {
+                    config.q_word.enable(&mut region, row)?;
+                    if row == 0 {
+                        config.q_first.enable(&mut region, row)?;
+                    } else {
+                        config.q_next.enable(&mut region, row)?;
+                    }
+
+                    let word = self
+                        .message
+                        .get(row)
+                        .copied()
+                        .unwrap_or(Value::known(F::ZERO));
+                    let flag = if row < length { F::ONE } else { F::ZERO };
+                    words.push(region.assign_advice(|| "word", config.word, row, || word)?);
+                    region.assign_advice(|| "flag", config.flag, row, || Value::known(flag))?;
+
+                    let previous = count
+                        .as_ref()
+                        .map_or(Value::known(F::ZERO), |c| c.value().copied());
+                    count = Some(region.assign_advice(
+                        || "count",
+                        config.count,
+                        row,
+                        || previous + Value::known(flag),
+                    )?);
+                }
+                Ok((words, count.unwrap()))
+            },
+        )?;
+
+        let hasher = Hash::<_, _, S, ConstantLength<MAX>, WIDTH, RATE>::init(
+            chip.clone(),
+            layouter.namespace(|| "init padded"),
+        )?;
+        let padded = hasher.hash(
+            layouter.namespace(|| "hash padded"),
+            words.try_into().unwrap(),
+        )?;
+
+        let hasher = Hash::<_, _, S, ConstantLength<2>, WIDTH, RATE>::init(
+            chip,
+            layouter.namespace(|| "init length"),
+        )?;
+        let output = hasher.hash(layouter.namespace(|| "hash length"), [padded, length])?;
+        layouter.constrain_instance(output.cell(), config.instance, 0)?;
+
+        layouter.assign_region(
+            || "constrain output",
+            |mut region| {
+                let expected_var =
+                    region.assign_advice(|| "load output", config.word, 0, || self.output)?;
+                region.constrain_equal(output.cell(), expected_var.cell())
+            },
+        )
+    }
+}
+
+/// Same as `PoseidonVarLenCircuit`, outside of the circuit.
+pub fn hash_var_len<
+    F: PrimeField,
+    S: Spec<F, WIDTH, RATE>,
+    const WIDTH: usize,
+    const RATE: usize,
+    const MAX: usize,
+>(
+    message: &[F],
+) -> Option<F> {
+    if message.len() > MAX {
+        return None;
+    }
+    let mut padded = [F::ZERO; MAX];
+    padded[..message.len()].copy_from_slice(message);
+    Some(hash_padded::<F, S, WIDTH, RATE, MAX>(
+        padded,
+        message.len() as u64,
+    ))
+}
+
+/// The digest of the padded words and a length, whether they make a message or not.
+pub fn hash_padded<
+    F: PrimeField,
+    S: Spec<F, WIDTH, RATE>,
+    const WIDTH: usize,
+    const RATE: usize,
+    const MAX: usize,
+>(
+    padded: [F; MAX],
+    length: u64,
+) -> F {
+    let padded = poseidon::Hash::<_, S, ConstantLength<MAX>, WIDTH, RATE>::init().hash(padded);
+    poseidon::Hash::<_, S, ConstantLength<2>, WIDTH, RATE>::init().hash([padded, F::from(length)])
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_poseidon::poseidon::primitives::P128Pow5T3 as OrchardNullifier;
+    use halo2_proofs::{dev::MockProver, halo2curves::pasta::Fp};
+    use rand::rngs::OsRng;
+
+    use super::*;
+    use crate::{
+        prove::{prove, Backend},
+        scenario::Verdict,
+        tamper::Tamper,
+    };
+
+    type VarLen = PoseidonVarLenCircuit<Fp, OrchardNullifier, 3, 2, 4>;
+
+    /// `[1, 2, third]` with the flags and the count of a message of length 2,
+    /// hashed as such: only the padding can give it away.
+    fn truncated(third: u64) -> (VarLen, Fp) {
+        let words = [1, 2, third, 0].map(Fp::from);
+        let output = hash_padded::<_, OrchardNullifier, 3, 2, 4>(words, 2);
+        let circuit = VarLen {
+            message: words[..3].iter().copied().map(Value::known).collect(),
+            output: Value::known(output),
+            length: Some(2),
+            _spec: PhantomData,
+        };
+        (circuit, output)
+    }
+
+    #[test]
+    fn dropped_word_breaks_only_the_padding() {
+        let (circuit, output) = truncated(3);
+        let failures = MockProver::run(8, &circuit, vec![vec![output]])
+            .unwrap()
+            .verify()
+            .unwrap_err();
+        assert!(failures
+            .iter()
+            .all(|failure| failure.to_string().contains("padded word")));
+    }
+
+    #[test]
+    fn zero_padding_is_the_shorter_message() {
+        let (circuit, output) = truncated(0);
+        let honest = [1, 2].map(Fp::from);
+        assert_eq!(
+            Some(output),
+            hash_var_len::<_, OrchardNullifier, 3, 2, 4>(&honest)
+        );
+        MockProver::run(8, &circuit, vec![vec![output]])
+            .unwrap()
+            .assert_satisfied();
+    }
+
+    #[test]
+    fn truncated_message_cannot_reuse_the_full_digest() {
+        let k = 8;
+        let params = Fp::setup(k, OsRng);
+        let full = [1, 2, 3].map(Fp::from);
+        let digest = hash_var_len::<_, OrchardNullifier, 3, 2, 4>(&full).unwrap();
+        let verdict = |circuit: &VarLen| {
+            prove(&params, k, circuit, vec![vec![digest]], &Tamper::new())
+                .unwrap()
+                .1
+                .verify(&params)
+        };
+
+        let honest = VarLen {
+            message: full.iter().copied().map(Value::known).collect(),
+            output: Value::known(digest),
+            length: None,
+            _spec: PhantomData,
+        };
+        assert_eq!(verdict(&honest), Verdict::Accept);
+
+        // the flags of `[1, 2]`, whether the dropped word is 3 or the 0 of the padding
+        for third in [3, 0] {
+            let (circuit, _) = truncated(third);
+            let circuit = VarLen {
+                output: Value::known(digest),
+                ..circuit
+            };
+            assert_eq!(verdict(&circuit), Verdict::Reject);
+        }
+    }
+}
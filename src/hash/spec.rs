@@ -0,0 +1,131 @@
+use halo2_poseidon::poseidon::primitives::{generate_constants, Spec};
+use halo2_proofs::halo2curves::{bn256::Fr, ff::Field};
+use std::sync::OnceLock;
+
+/// Poseidon with the x^5 S-box over bn256, 128 bits of security.
+///
+/// `halo2_poseidon` only ships the pasta constants (`P128Pow5T3`), these are
+/// generated with its Grain LFSR from the round numbers of the Poseidon paper:
+/// 8 full rounds, 57 partial rounds for a width of 3 and 60 for a width of 5.
+/// That is the permutation of circomlib, see the `circomlib_*` tests, but not its hash:
+/// circomlib puts a 0 capacity first and the message after it, `ConstantLength` puts
+/// the length in the capacity and the message in the first words of the state.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Pow5Bn256<const WIDTH: usize, const RATE: usize>;
+
+pub type P128Pow5T3Bn256 = Pow5Bn256<3, 2>;
+pub type P128Pow5T5Bn256 = Pow5Bn256<5, 4>;
+
+type Constants<const WIDTH: usize> = (Vec<[Fr; WIDTH]>, [[Fr; WIDTH]; WIDTH], [[Fr; WIDTH]; WIDTH]);
+
+// generating the constants takes a while, and the chip asks for them at every `configure`
+static T3: OnceLock<Constants<3>> = OnceLock::new();
+static T5: OnceLock<Constants<5>> = OnceLock::new();
+
+impl Spec<Fr, 3, 2> for P128Pow5T3Bn256 {
+    fn full_rounds() -> usize {
+        8
+    }
+
+    fn partial_rounds() -> usize {
+        57
+    }
+
+    fn sbox(val: Fr) -> Fr {
+        val.pow_vartime([5])
+    }
+
+    fn secure_mds() -> usize {
+        0
+    }
+
+    fn constants() -> Constants<3> {
+        T3.get_or_init(generate_constants::<_, Self, 3, 2>).clone()
+    }
+}
+
+impl Spec<Fr, 5, 4> for P128Pow5T5Bn256 {
+    fn full_rounds() -> usize {
+        8
+    }
+
+    fn partial_rounds() -> usize {
+        60
+    }
+
+    fn sbox(val: Fr) -> Fr {
+        val.pow_vartime([5])
+    }
+
+    fn secure_mds() -> usize {
+        0
+    }
+
+    fn constants() -> Constants<5> {
+        T5.get_or_init(generate_constants::<_, Self, 5, 4>).clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_poseidon::poseidon::primitives::{ConstantLength, Hash};
+
+    use super::*;
+    use crate::{hash::sponge::NativeSponge, scenario::parse_field};
+
+    fn fr(hex: &str) -> Fr {
+        parse_field(hex).unwrap()
+    }
+
+    #[test]
+    fn width3_digest() {
+        let message = [1, 2, 3].map(Fr::from);
+        let output = Hash::<_, P128Pow5T3Bn256, ConstantLength<3>, 3, 2>::init().hash(message);
+        assert_eq!(
+            output,
+            fr("0x264f7d330904158e85a103eb842527d2f2d8165df37c1d4bb04aa5755f31ebe6")
+        );
+    }
+
+    #[test]
+    fn width5_digest() {
+        let message = [1, 2, 3, 4].map(Fr::from);
+        let output = Hash::<_, P128Pow5T5Bn256, ConstantLength<4>, 5, 4>::init().hash(message);
+        assert_eq!(
+            output,
+            fr("0x093ea7b03e2aa7ed3bdc8ec3be434754a6f8dfc8829157be13319c4f213c40f0")
+        );
+    }
+
+    /// The first word of the permutation of `state`: `state[WIDTH - 1]` is the capacity
+    /// of the sponge, the other words are absorbed.
+    fn permute<S: Spec<Fr, WIDTH, RATE>, const WIDTH: usize, const RATE: usize>(
+        state: [u64; WIDTH],
+    ) -> Fr {
+        let mut sponge = NativeSponge::<Fr, S, WIDTH, RATE>::new(Fr::from(state[RATE]));
+        for word in state[..RATE].iter() {
+            sponge.absorb(Fr::from(*word));
+        }
+        sponge.squeeze()
+    }
+
+    // circomlib `poseidon([1, 2])` and `poseidon([1, 2, 3, 4])`, the first word of the
+    // permutation of `[0, 1, 2]` and `[0, 1, 2, 3, 4]`: test/poseidoncircuit.js of
+    // https://github.com/iden3/circomlib, also in poseidon_test.go of
+    // https://github.com/iden3/go-iden3-crypto
+    #[test]
+    fn circomlib_width3() {
+        assert_eq!(
+            permute::<P128Pow5T3Bn256, 3, 2>([0, 1, 2]),
+            fr("0x115cc0f5e7d690413df64c6b9662e9cf2a3617f2743245519e19607a4417189a")
+        );
+    }
+
+    #[test]
+    fn circomlib_width5() {
+        assert_eq!(
+            permute::<P128Pow5T5Bn256, 5, 4>([0, 1, 2, 3, 4]),
+            fr("0x299c867db6c1fdd79dcefa40e4510b9837e60ebb1ce0663dbaa525df65250465")
+        );
+    }
+}
@@ -0,0 +1,471 @@
+use halo2_poseidon::poseidon::{
+    primitives::Spec, PoseidonInstructions, Pow5Chip, Pow5Config, StateWord,
+};
+use halo2_proofs::{
+    circuit::{AssignedCell, Layouter, SimpleFloorPlanner, Value},
+    halo2curves::ff::PrimeField,
+    plonk::{Advice, Circuit, Column, ConstraintSystem, ErrorFront, Selector},
+    poly::Rotation,
+};
+use serde::Deserialize;
+use std::marker::PhantomData;
+
+/// The `Pow5Chip` and a gate adding the inputs to the state: `a + b = sum` on one row.
+#[derive(Clone, Debug)]
+pub struct SpongeConfig<F: PrimeField, const WIDTH: usize, const RATE: usize> {
+    pub pow5config: Pow5Config<F, WIDTH, RATE>,
+    add: [Column<Advice>; 3],
+    q_add: Selector,
+}
+
+#[derive(Debug)]
+enum Mode<T> {
+    /// Inputs waiting to be added to the rate part of the state.
+    Absorbing(Vec<T>),
+    /// Outputs of the last permutation not squeezed yet.
+    Squeezing(Vec<T>),
+}
+
+/// A duplex sponge on the `Pow5Chip`, absorbs and squeezes in any order.
+///
+/// Squeezing after absorbing adds the pending inputs and permutes, the missing inputs
+/// count as 0, like the padding of `ConstantLength`. Squeezing more than `RATE` elements
+/// permutes again without input, absorbing after squeezing starts a new block.
+/// With the capacity of `ConstantLength<L>`, absorbing `L` elements then squeezing one
+/// is `primitives::Hash::hash`.
+#[derive(Debug)]
+pub struct PoseidonSponge<
+    F: PrimeField,
+    S: Spec<F, WIDTH, RATE>,
+    const WIDTH: usize,
+    const RATE: usize,
+> {
+    config: SpongeConfig<F, WIDTH, RATE>,
+    state: [AssignedCell<F, F>; WIDTH],
+    mode: Mode<AssignedCell<F, F>>,
+    _spec: PhantomData<S>,
+}
+
+impl<F, S, const WIDTH: usize, const RATE: usize> PoseidonSponge<F, S, WIDTH, RATE>
+where
+    F: PrimeField,
+    S: Spec<F, WIDTH, RATE>,
+{
+    /// The circuit must also enable a constant column, the initial state is made of constants.
+    pub fn configure(
+        meta: &mut ConstraintSystem<F>,
+        pow5config: Pow5Config<F, WIDTH, RATE>,
+        add: [Column<Advice>; 3],
+    ) -> SpongeConfig<F, WIDTH, RATE> {
+        for column in add {
+            meta.enable_equality(column);
+        }
+        let q_add = meta.selector();
+
+        meta.create_gate("add input", |meta| {
+            let s = meta.query_selector(q_add);
+            let a = meta.query_advice(add[0], Rotation::cur());
+            let b = meta.query_advice(add[1], Rotation::cur());
+            let sum = meta.query_advice(add[2], Rotation::cur());
+            vec![s * (a + b - sum)]
+        });
+
+        SpongeConfig {
+            pow5config,
+            add,
+            q_add,
+        }
+    }
+
+    /// A state of zeros, except the first element of the capacity.
+    pub fn new(
+        config: SpongeConfig<F, WIDTH, RATE>,
+        mut layouter: impl Layouter<F>,
+        capacity: F,
+    ) -> Result<Self, ErrorFront> {
+        let state = layouter.assign_region(
+            || "sponge init",
+            |mut region| {
+                let state = (0..WIDTH)
+                    .map(|i| {
+                        let value = if i == RATE { capacity } else { F::ZERO };
+                        region.assign_advice_from_constant(
+                            || format!("state_{}", i),
+                            config.pow5config.state[i],
+                            0,
+                            value,
+                        )
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(state.try_into().unwrap())
+            },
+        )?;
+
+        Ok(Self {
+            config,
+            state,
+            mode: Mode::Absorbing(vec![]),
+            _spec: PhantomData,
+        })
+    }
+
+    pub fn absorb(
+        &mut self,
+        mut layouter: impl Layouter<F>,
+        input: AssignedCell<F, F>,
+    ) -> Result<(), ErrorFront> {
+        match &mut self.mode {
+            Mode::Absorbing(inputs) if inputs.len() < RATE => inputs.push(input),
+            Mode::Absorbing(_) => {
+                self.duplex(layouter.namespace(|| "absorb"))?;
+                self.mode = Mode::Absorbing(vec![input]);
+            }
+            Mode::Squeezing(_) => self.mode = Mode::Absorbing(vec![input]),
+        }
+        Ok(())
+    }
+
+    pub fn squeeze(
+        &mut self,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<AssignedCell<F, F>, ErrorFront> {
+        if !matches!(&self.mode, Mode::Squeezing(outputs) if !outputs.is_empty()) {
+            self.duplex(layouter.namespace(|| "squeeze"))?;
+        }
+        match &mut self.mode {
+            Mode::Squeezing(outputs) => Ok(outputs.remove(0)),
+            Mode::Absorbing(_) => unreachable!("duplex always leaves the sponge squeezing"),
+        }
+    }
+
+    /// Adds the pending inputs, if any, to the rate part and permutes.
+    fn duplex(&mut self, mut layouter: impl Layouter<F>) -> Result<(), ErrorFront> {
+        let inputs = match std::mem::replace(&mut self.mode, Mode::Absorbing(vec![])) {
+            Mode::Absorbing(inputs) => inputs,
+            Mode::Squeezing(_) => vec![],
+        };
+
+        for (i, input) in inputs.into_iter().enumerate() {
+            let config = &self.config;
+            let word = &self.state[i];
+            self.state[i] = layouter.assign_region(
+                || "add input",
+                |mut region| {
+                    config.q_add.enable(&mut region, 0)?;
+                    word.copy_advice(|| "state", &mut region, config.add[0], 0)?;
+                    input.copy_advice(|| "input", &mut region, config.add[1], 0)?;
+                    let sum = word.value().copied() + input.value().copied();
+                    region.assign_advice(|| "sum", config.add[2], 0, || sum)
+                },
+            )?;
+        }
+
+        let chip = Pow5Chip::construct(self.config.pow5config.clone());
+        let state = self.state.clone().map(StateWord::from);
+        let state = <Pow5Chip<F, WIDTH, RATE> as PoseidonInstructions<F, S, WIDTH, RATE>>::permute(
+            &chip,
+            &mut layouter.namespace(|| "permute"),
+            &state,
+        )?;
+        self.state = state.map(AssignedCell::from);
+        self.mode = Mode::Squeezing(self.state[..RATE].to_vec());
+        Ok(())
+    }
+}
+
+/// `PoseidonSponge` outside of the circuit, with the same permutation as `primitives`.
+#[derive(Debug)]
+pub struct NativeSponge<
+    F: PrimeField,
+    S: Spec<F, WIDTH, RATE>,
+    const WIDTH: usize,
+    const RATE: usize,
+> {
+    state: [F; WIDTH],
+    mode: Mode<F>,
+    round_constants: Vec<[F; WIDTH]>,
+    mds: [[F; WIDTH]; WIDTH],
+    _spec: PhantomData<S>,
+}
+
+impl<F, S, const WIDTH: usize, const RATE: usize> NativeSponge<F, S, WIDTH, RATE>
+where
+    F: PrimeField,
+    S: Spec<F, WIDTH, RATE>,
+{
+    pub fn new(capacity: F) -> Self {
+        let (round_constants, mds, _) = S::constants();
+        let mut state = [F::ZERO; WIDTH];
+        state[RATE] = capacity;
+        Self {
+            state,
+            mode: Mode::Absorbing(vec![]),
+            round_constants,
+            mds,
+            _spec: PhantomData,
+        }
+    }
+
+    pub fn absorb(&mut self, input: F) {
+        match &mut self.mode {
+            Mode::Absorbing(inputs) if inputs.len() < RATE => inputs.push(input),
+            Mode::Absorbing(_) => {
+                self.duplex();
+                self.mode = Mode::Absorbing(vec![input]);
+            }
+            Mode::Squeezing(_) => self.mode = Mode::Absorbing(vec![input]),
+        }
+    }
+
+    pub fn squeeze(&mut self) -> F {
+        if !matches!(&self.mode, Mode::Squeezing(outputs) if !outputs.is_empty()) {
+            self.duplex();
+        }
+        match &mut self.mode {
+            Mode::Squeezing(outputs) => outputs.remove(0),
+            Mode::Absorbing(_) => unreachable!("duplex always leaves the sponge squeezing"),
+        }
+    }
+
+    fn duplex(&mut self) {
+        if let Mode::Absorbing(inputs) = &self.mode {
+            for (word, input) in self.state.iter_mut().zip(inputs) {
+                *word += input;
+            }
+        }
+        self.permute();
+        self.mode = Mode::Squeezing(self.state[..RATE].to_vec());
+    }
+
+    /// `R_F / 2` full rounds, `R_P` partial rounds with the S-box on the first word only,
+    /// then `R_F / 2` full rounds.
+    fn permute(&mut self) {
+        let half_full = S::full_rounds() / 2;
+        let rounds = S::full_rounds() + S::partial_rounds();
+        for (round, constants) in self.round_constants.iter().enumerate().take(rounds) {
+            let full = round < half_full || round >= half_full + S::partial_rounds();
+            for (i, (word, c)) in self.state.iter_mut().zip(constants).enumerate() {
+                *word += c;
+                if full || i == 0 {
+                    *word = S::sbox(*word);
+                }
+            }
+            let state = self.state;
+            for (word, row) in self.state.iter_mut().zip(self.mds.iter()) {
+                *word = row
+                    .iter()
+                    .zip(state.iter())
+                    .fold(F::ZERO, |acc, (m, s)| acc + *m * s);
+            }
+        }
+    }
+}
+
+/// What the sponge circuit does, in order.
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub enum SpongeOp<F: PrimeField> {
+    Absorb(#[serde(deserialize_with = "crate::inputs::value")] Value<F>),
+    /// The element we expect to squeeze.
+    Squeeze(#[serde(deserialize_with = "crate::inputs::value")] Value<F>),
+}
+
+/// Runs `ops` on a `PoseidonSponge`, and checks every squeezed element.
+#[derive(Debug, Default, Clone, Deserialize)]
+pub struct SpongeCircuit<
+    F: PrimeField,
+    S: Spec<F, WIDTH, RATE>,
+    const WIDTH: usize,
+    const RATE: usize,
+> {
+    /// The first element of the capacity, e.g. `L << 64` for `ConstantLength<L>`.
+    #[serde(default)]
+    pub capacity: u128,
+    #[serde(default)]
+    pub ops: Vec<SpongeOp<F>>,
+    #[serde(skip)]
+    pub _spec: PhantomData<S>,
+}
+
+impl<F, S, const WIDTH: usize, const RATE: usize> Circuit<F> for SpongeCircuit<F, S, WIDTH, RATE>
+where
+    F: PrimeField,
+    S: Spec<F, WIDTH, RATE>,
+{
+    type Config = SpongeConfig<F, WIDTH, RATE>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self {
+            capacity: self.capacity,
+            ops: self
+                .ops
+                .iter()
+                .map(|op| match op {
+                    SpongeOp::Absorb(_) => SpongeOp::Absorb(Value::unknown()),
+                    SpongeOp::Squeeze(_) => SpongeOp::Squeeze(Value::unknown()),
+                })
+                .collect(),
+            _spec: PhantomData,
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        let state = (0..WIDTH).map(|_| meta.advice_column()).collect::<Vec<_>>();
+        let partial_sbox = meta.advice_column();
+
+        let rc_a = (0..WIDTH).map(|_| meta.fixed_column()).collect::<Vec<_>>();
+        let rc_b = (0..WIDTH).map(|_| meta.fixed_column()).collect::<Vec<_>>();
+
+        meta.enable_constant(rc_b[0]);
+
+        let pow5config = Pow5Chip::configure::<S>(
+            meta,
+            state.try_into().unwrap(),
+            partial_sbox,
+            rc_a.try_into().unwrap(),
+            rc_b.try_into().unwrap(),
+        );
+
+        let add = [(); 3].map(|_| meta.advice_column());
+        PoseidonSponge::<F, S, WIDTH, RATE>::configure(meta, pow5config, add)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), ErrorFront> {
+        let column = config.add[0];
+        let mut sponge = PoseidonSponge::<F, S, WIDTH, RATE>::new(
+            config,
+            layouter.namespace(|| "sponge"),
+            F::from_u128(self.capacity),
+        )?;
+
+        for op in self.ops.iter() {
+            match op {
+                SpongeOp::Absorb(value) => {
+                    let input = layouter.assign_region(
+                        || "load input",
+                        |mut region| region.assign_advice(|| "input", column, 0, || *value),
+                    )?;
+                    sponge.absorb(layouter.namespace(|| "absorb"), input)?;
+                }
+                SpongeOp::Squeeze(expected) => {
+                    let output = sponge.squeeze(layouter.namespace(|| "squeeze"))?;
+                    layouter.assign_region(
+                        || "constrain output",
+                        |mut region| {
+                            let expected_var =
+                                region.assign_advice(|| "load output", column, 0, || *expected)?;
+                            region.constrain_equal(output.cell(), expected_var.cell())
+                        },
+                    )?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_poseidon::poseidon::primitives::{
+        ConstantLength, Hash, P128Pow5T3 as OrchardNullifier,
+    };
+    use halo2_proofs::{arithmetic::Field, dev::MockProver, halo2curves::pasta::Fp};
+
+    use super::*;
+    use crate::layout::Layout;
+
+    type Native = NativeSponge<Fp, OrchardNullifier, 3, 2>;
+    type Sponge = SpongeCircuit<Fp, OrchardNullifier, 3, 2>;
+    const RATE: usize = 2;
+
+    /// Runs `script` on a `NativeSponge`, `Some` absorbs and `None` squeezes,
+    /// and expects the circuit to squeeze the same elements.
+    fn run(capacity: u128, script: &[Option<Fp>]) -> (Sponge, Vec<Fp>) {
+        let mut native = Native::new(Fp::from_u128(capacity));
+        let mut squeezed = vec![];
+        let ops = script
+            .iter()
+            .map(|op| match op {
+                Some(input) => {
+                    native.absorb(*input);
+                    SpongeOp::Absorb(Value::known(*input))
+                }
+                None => {
+                    squeezed.push(native.squeeze());
+                    SpongeOp::Squeeze(Value::known(*squeezed.last().unwrap()))
+                }
+            })
+            .collect();
+        let circuit = Sponge {
+            capacity,
+            ops,
+            _spec: PhantomData,
+        };
+        (circuit, squeezed)
+    }
+
+    /// The same ops, expecting `value` from the op at `index` instead.
+    fn squeezing(circuit: &Sponge, index: usize, value: Fp) -> Sponge {
+        let mut ops = circuit.ops.clone();
+        ops[index] = SpongeOp::Squeeze(Value::known(value));
+        Sponge {
+            capacity: circuit.capacity,
+            ops,
+            _spec: PhantomData,
+        }
+    }
+
+    fn prover(circuit: &Sponge) -> MockProver<Fp> {
+        let k = Layout::<Fp>::row_count(circuit).unwrap().k;
+        MockProver::run(k, circuit, vec![]).unwrap()
+    }
+
+    /// Absorbs `L` words with the capacity of `ConstantLength<L>`, then squeezes past the rate.
+    fn constant_length<const L: usize>() {
+        let message: [Fp; L] = std::array::from_fn(|i| Fp::from(i as u64 + 1));
+        let mut script = message.map(Some).to_vec();
+        script.extend([None; RATE + 1]);
+        let (circuit, squeezed) = run((L as u128) << 64, &script);
+
+        let output = Hash::<_, OrchardNullifier, ConstantLength<L>, 3, RATE>::init().hash(message);
+        assert_eq!(squeezed[0], output, "length {}", L);
+        prover(&circuit).assert_satisfied();
+
+        // every squeezed cell is checked, not only the first one
+        for (i, output) in squeezed.iter().enumerate() {
+            let wrong = squeezing(&circuit, L + i, *output + Fp::ONE);
+            assert!(
+                prover(&wrong).verify().is_err(),
+                "length {}, squeeze {}",
+                L,
+                i
+            );
+        }
+    }
+
+    #[test]
+    fn sponge_is_constant_length_hash() {
+        constant_length::<1>();
+        constant_length::<RATE>();
+        constant_length::<{ RATE + 1 }>();
+        // 4 blocks, the last one padded
+        constant_length::<{ 3 * RATE + 1 }>();
+    }
+
+    #[test]
+    fn interleaved_absorb_and_squeeze() {
+        let [a, b, c] = [1, 2, 3].map(|m| Some(Fp::from(m)));
+        let script = [a, None, b, c, a, None, None, None, c, None];
+        let (circuit, squeezed) = run(0, &script);
+        assert_eq!(squeezed.len(), 5);
+        prover(&circuit).assert_satisfied();
+
+        // the squeezes of the second block are not those of the first one
+        let stale = squeezing(&circuit, 5, squeezed[0]);
+        assert!(prover(&stale).verify().is_err());
+    }
+}
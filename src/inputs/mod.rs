@@ -0,0 +1,211 @@
+//! Circuit inputs read from JSON, so new attack inputs don't need a recompile.
+//!
+//! A file holds the fields of the circuit struct under `witness`,
+//! and the instance columns under `instances`:
+//!
+//! ```json
+//! {
+//!     "witness": { "a": "2", "b": "0x3" },
+//!     "instances": [["6"]]
+//! }
+//! ```
+//!
+//! Field elements are JSON numbers, or strings in decimal or `0x` hex
+//! with an optional leading `-`, see `parse_field`.
+//! A `null` element is an unknown `Value`.
+
+use halo2_proofs::{circuit::Value, halo2curves::ff::PrimeField};
+use serde::{de::DeserializeOwned, Deserialize, Deserializer};
+use std::{fmt, fs, io, path::Path};
+
+use crate::scenario::parse_field;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+enum Element {
+    Number(u64),
+    Text(String),
+}
+
+impl Element {
+    fn to_field<F: PrimeField>(&self) -> Result<F, String> {
+        match self {
+            Element::Number(n) => Ok(F::from(*n)),
+            Element::Text(s) => parse_field(s),
+        }
+    }
+}
+
+fn to_value<F: PrimeField, E: serde::de::Error>(element: Option<Element>) -> Result<Value<F>, E> {
+    match element {
+        Some(element) => element.to_field().map(Value::known).map_err(E::custom),
+        None => Ok(Value::unknown()),
+    }
+}
+
+/// `deserialize_with` for a `Value<F>` field.
+pub fn value<'de, D: Deserializer<'de>, F: PrimeField>(
+    deserializer: D,
+) -> Result<Value<F>, D::Error> {
+    to_value(Option::<Element>::deserialize(deserializer)?)
+}
+
+/// `deserialize_with` for a `Vec<Value<F>>` field.
+pub fn values<'de, D: Deserializer<'de>, F: PrimeField>(
+    deserializer: D,
+) -> Result<Vec<Value<F>>, D::Error> {
+    Vec::<Option<Element>>::deserialize(deserializer)?
+        .into_iter()
+        .map(to_value::<F, D::Error>)
+        .collect()
+}
+
+/// `deserialize_with` for a `Value<[F; L]>` field, e.g. the message of a hash.
+pub fn value_array<'de, D: Deserializer<'de>, F: PrimeField, const L: usize>(
+    deserializer: D,
+) -> Result<Value<[F; L]>, D::Error> {
+    use serde::de::Error;
+
+    let elements = match Option::<Vec<Element>>::deserialize(deserializer)? {
+        Some(elements) => elements,
+        None => return Ok(Value::unknown()),
+    };
+    if elements.len() != L {
+        return Err(D::Error::invalid_length(
+            elements.len(),
+            &L.to_string().as_str(),
+        ));
+    }
+    let fields = elements
+        .iter()
+        .map(|e| e.to_field().map_err(D::Error::custom))
+        .collect::<Result<Vec<F>, _>>()?;
+    Ok(Value::known(fields.try_into().unwrap()))
+}
+
+#[derive(Deserialize)]
+struct InputFile<C> {
+    witness: C,
+    #[serde(default)]
+    instances: Vec<Vec<Element>>,
+}
+
+#[derive(Debug)]
+pub enum InputError {
+    Io(io::Error),
+    Json(serde_json::Error),
+    InvalidInstance(String),
+}
+
+impl fmt::Display for InputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InputError::Io(err) => write!(f, "can't read the input file: {}", err),
+            InputError::Json(err) => write!(f, "invalid input file: {}", err),
+            InputError::InvalidInstance(err) => write!(f, "invalid instance: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for InputError {}
+
+/// Reads a circuit and its instance columns from the JSON string `json`.
+pub fn from_str<F: PrimeField, C: DeserializeOwned>(
+    json: &str,
+) -> Result<(C, Vec<Vec<F>>), InputError> {
+    let file: InputFile<C> = serde_json::from_str(json).map_err(InputError::Json)?;
+    let instances = file
+        .instances
+        .iter()
+        .map(|column| {
+            column
+                .iter()
+                .map(Element::to_field::<F>)
+                .collect::<Result<Vec<F>, String>>()
+        })
+        .collect::<Result<_, _>>()
+        .map_err(InputError::InvalidInstance)?;
+    Ok((file.witness, instances))
+}
+
+pub fn load<F: PrimeField, C: DeserializeOwned>(
+    path: &Path,
+) -> Result<(C, Vec<Vec<F>>), InputError> {
+    from_str(&fs::read_to_string(path).map_err(InputError::Io)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::halo2curves::{bn256::Fr, ff::Field};
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::mul::mul1;
+
+    #[derive(Deserialize)]
+    struct Witness {
+        #[serde(default, deserialize_with = "value")]
+        a: Value<Fr>,
+        #[serde(default, deserialize_with = "value")]
+        missing: Value<Fr>,
+        #[serde(deserialize_with = "values")]
+        path: Vec<Value<Fr>>,
+        #[serde(deserialize_with = "value_array")]
+        message: Value<[Fr; 3]>,
+    }
+
+    fn known<T>(value: Value<T>) -> Option<T> {
+        let mut known = None;
+        let _ = value.map(|v| known = Some(v));
+        known
+    }
+
+    #[test]
+    fn witness_and_instances() {
+        let json = r#"{
+            "witness": {
+                "a": "-1",
+                "path": [1, "0x2", null],
+                "message": ["1", 2, "0x3"]
+            },
+            "instances": [["12", 13], []]
+        }"#;
+        let (witness, instances) = from_str::<Fr, Witness>(json).unwrap();
+
+        assert_eq!(known(witness.a), Some(-Fr::ONE));
+        assert_eq!(known(witness.missing), None);
+        assert_eq!(
+            witness.path.into_iter().map(known).collect::<Vec<_>>(),
+            vec![Some(Fr::from(1)), Some(Fr::from(2)), None]
+        );
+        assert_eq!(known(witness.message), Some([1, 2, 3].map(Fr::from)));
+        assert_eq!(instances, vec![vec![Fr::from(12), Fr::from(13)], vec![]]);
+    }
+
+    #[test]
+    fn wrong_array_length() {
+        let json = r#"{ "witness": { "path": [], "message": [1, 2] } }"#;
+        assert!(matches!(
+            from_str::<Fr, Witness>(json),
+            Err(InputError::Json(_))
+        ));
+    }
+
+    #[test]
+    fn invalid_instance() {
+        let json = r#"{ "witness": { "path": [], "message": null }, "instances": [["x"]] }"#;
+        assert!(matches!(
+            from_str::<Fr, Witness>(json),
+            Err(InputError::InvalidInstance(_))
+        ));
+    }
+
+    #[test]
+    fn shipped_input_file() {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("inputs/mul1-tampered-out.json");
+        let (circuit, instances) = load::<Fr, mul1::MultiplicationCircuit>(&path).unwrap();
+        assert_eq!(known(circuit.a), Some(Fr::from(2)));
+        assert_eq!(known(circuit.b), Some(Fr::from(3)));
+        assert_eq!(instances, vec![vec![Fr::from(12)]]);
+    }
+}
@@ -0,0 +1,250 @@
+use halo2_proofs::{
+    arithmetic::Field,
+    circuit::Value,
+    plonk::{
+        Advice, Any, Assigned, Assignment, Challenge, Circuit, Column, ConstraintSystem,
+        ErrorFront, Fixed, FloorPlanner, Instance, Selector,
+    },
+};
+use std::{collections::HashMap, marker::PhantomData};
+
+/// An advice cell as the circuit named it, and where the floor planner put it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NamedCell {
+    pub region: String,
+    pub annotation: String,
+    pub column: Column<Advice>,
+    pub row: usize,
+}
+
+/// Everything the floor planner did while synthesizing a circuit,
+/// without caring about the values.
+///
+/// `MockProver` forgets the region and annotation names once the witness is assigned,
+/// so we run the floor planner a second time with our own `Assignment` to remember them.
+#[derive(Debug, Clone)]
+pub struct Layout<F: Field> {
+    pub advice: Vec<NamedCell>,
+    pub copies: Vec<((Column<Any>, usize), (Column<Any>, usize))>,
+    pub selectors: Vec<(Selector, usize)>,
+    /// the fixed cells assigned by the circuit, by (column, row)
+    pub fixed: HashMap<(usize, usize), F>,
+    /// one past the last row assigned in any column, lookup tables included
+    pub rows: usize,
+    region: Option<String>,
+    _marker: PhantomData<F>,
+}
+
+/// How many rows a circuit uses, and the smallest `k` that has room for them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RowCount {
+    pub rows: usize,
+    pub k: u32,
+}
+
+/// A cell of any column, as the permutation argument sees it.
+pub type AnyCell = (Column<Any>, usize);
+
+/// Cells grouped by copy constraints: two cells with the same root must hold the same value.
+#[derive(Debug, Clone, Default)]
+pub struct CopyClasses {
+    parent: HashMap<AnyCell, AnyCell>,
+}
+
+impl CopyClasses {
+    pub fn root(&self, mut cell: AnyCell) -> AnyCell {
+        while let Some(p) = self.parent.get(&cell) {
+            cell = *p;
+        }
+        cell
+    }
+
+    pub fn advice_root(&self, cell: &NamedCell) -> AnyCell {
+        self.root((Column::<Any>::from(cell.column), cell.row))
+    }
+}
+
+impl<F: Field> Layout<F> {
+    pub fn synthesize<C: Circuit<F>>(circuit: &C) -> Result<Self, ErrorFront> {
+        Self::configure_and_synthesize(circuit).map(|(_, layout)| layout)
+    }
+
+    /// Also returns the constraint system, for the analyses which need the gates.
+    pub fn configure_and_synthesize<C: Circuit<F>>(
+        circuit: &C,
+    ) -> Result<(ConstraintSystem<F>, Self), ErrorFront> {
+        let mut cs = ConstraintSystem::default();
+        let config = C::configure(&mut cs);
+
+        let mut layout = Self {
+            advice: vec![],
+            copies: vec![],
+            selectors: vec![],
+            fixed: HashMap::new(),
+            rows: 0,
+            region: None,
+            _marker: PhantomData,
+        };
+        C::FloorPlanner::synthesize(&mut layout, circuit, config, cs.constants().clone())?;
+        Ok((cs, layout))
+    }
+
+    /// Instead of trying `MockProver` with bigger and bigger `k`: the last rows of the
+    /// `2^k` are for blinding, the circuit must fit before them.
+    pub fn row_count<C: Circuit<F>>(circuit: &C) -> Result<RowCount, ErrorFront> {
+        let (cs, layout) = Self::configure_and_synthesize(circuit)?;
+        let needed = (layout.rows + cs.blinding_factors() + 1).max(cs.minimum_rows());
+        Ok(RowCount {
+            rows: layout.rows,
+            k: needed.next_power_of_two().trailing_zeros(),
+        })
+    }
+
+    fn use_row(&mut self, row: usize) {
+        self.rows = self.rows.max(row + 1);
+    }
+
+    pub fn copy_classes(&self) -> CopyClasses {
+        let mut classes = CopyClasses::default();
+        for (left, right) in self.copies.iter() {
+            let (l, r) = (classes.root(*left), classes.root(*right));
+            if l != r {
+                classes.parent.insert(l, r);
+            }
+        }
+        classes
+    }
+
+    /// All the cells assigned with this region name and annotation, in assignment order.
+    pub fn find<'a>(
+        &'a self,
+        region: &'a str,
+        annotation: &'a str,
+    ) -> impl Iterator<Item = &'a NamedCell> {
+        self.advice
+            .iter()
+            .filter(move |c| c.region == region && c.annotation == annotation)
+    }
+
+    pub fn cell_at(&self, column: usize, row: usize) -> Option<&NamedCell> {
+        self.advice
+            .iter()
+            .find(|c| c.column.index() == column && c.row == row)
+    }
+}
+
+impl<F: Field> Assignment<F> for Layout<F> {
+    fn enter_region<NR, N>(&mut self, name_fn: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        self.region = Some(name_fn().into());
+    }
+
+    fn exit_region(&mut self) {
+        self.region = None;
+    }
+
+    fn enable_selector<A, AR>(
+        &mut self,
+        _annotation: A,
+        selector: &Selector,
+        row: usize,
+    ) -> Result<(), ErrorFront>
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.use_row(row);
+        self.selectors.push((*selector, row));
+        Ok(())
+    }
+
+    fn query_instance(
+        &self,
+        _column: Column<Instance>,
+        _row: usize,
+    ) -> Result<Value<F>, ErrorFront> {
+        Ok(Value::unknown())
+    }
+
+    fn assign_advice<V, VR, A, AR>(
+        &mut self,
+        annotation: A,
+        column: Column<Advice>,
+        row: usize,
+        _to: V,
+    ) -> Result<(), ErrorFront>
+    where
+        V: FnOnce() -> Value<VR>,
+        VR: Into<Assigned<F>>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.use_row(row);
+        self.advice.push(NamedCell {
+            region: self.region.clone().unwrap_or_default(),
+            annotation: annotation().into(),
+            column,
+            row,
+        });
+        Ok(())
+    }
+
+    fn assign_fixed<V, VR, A, AR>(
+        &mut self,
+        _annotation: A,
+        column: Column<Fixed>,
+        row: usize,
+        to: V,
+    ) -> Result<(), ErrorFront>
+    where
+        V: FnOnce() -> Value<VR>,
+        VR: Into<Assigned<F>>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.use_row(row);
+        // fixed values are known at keygen, `Value` only lets us out through `map`
+        to().map(|v| {
+            self.fixed
+                .insert((column.index(), row), v.into().evaluate());
+        });
+        Ok(())
+    }
+
+    fn copy(
+        &mut self,
+        left_column: Column<Any>,
+        left_row: usize,
+        right_column: Column<Any>,
+        right_row: usize,
+    ) -> Result<(), ErrorFront> {
+        self.copies
+            .push(((left_column, left_row), (right_column, right_row)));
+        Ok(())
+    }
+
+    fn fill_from_row(
+        &mut self,
+        _column: Column<Fixed>,
+        _row: usize,
+        _to: Value<Assigned<F>>,
+    ) -> Result<(), ErrorFront> {
+        Ok(())
+    }
+
+    fn get_challenge(&self, _challenge: Challenge) -> Value<F> {
+        Value::unknown()
+    }
+
+    fn push_namespace<NR, N>(&mut self, _name_fn: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+    }
+
+    fn pop_namespace(&mut self, _gadget_name: Option<String>) {}
+}
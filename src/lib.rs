@@ -0,0 +1,13 @@
+pub mod analysis;
+pub mod base;
+pub mod casino;
+pub mod chips;
+pub mod hash;
+pub mod inputs;
+pub mod layout;
+pub mod merkle;
+pub mod mul;
+pub mod prove;
+pub mod scenario;
+pub mod square_root;
+pub mod tamper;
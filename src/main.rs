@@ -1,20 +1,207 @@
-mod casino;
-mod hash;
-mod merkle;
-mod mul;
-mod square_root;
-
-fn main() {
-    mul::multiplication();
-    casino::casino();
-    square_root::square_root();
-
-    merkle::merke_nohash0();
-    merkle::merke_nohash1();
-    merkle::merke_nohash2();
-    merkle::merke_nohash3();
-    merkle::merke_nohash4();
-
-    hash::hash_circuit();
-    merkle::merke_circuit_with_hash();
+use halo2_soundness_bugs::{
+    prove::ParamsCache,
+    scenario::{self, DynScenario, Registry, Report, RunOptions, Verdict},
+    tamper::CellTarget,
+};
+use std::{env, path::Path, process::ExitCode};
+
+const USAGE: &str = "usage:
+    halo2-soundness-bugs                 run every scenario, mock and real proofs
+    halo2-soundness-bugs list            print the registered scenarios
+    halo2-soundness-bugs run <name>      [--k <k>] [--input <file.json>] [--instance <v1,v2,..>]... [--real]
+    halo2-soundness-bugs tamper <name>   --cell <region/annotation=value>... [run options]
+    halo2-soundness-bugs analyze <name>  search for underconstrained cells
+    halo2-soundness-bugs coverage <name> which advice cells gates, lookups and copies reach
+    halo2-soundness-bugs rows [pattern]  rows used and smallest k of the matching scenarios
+
+--input replaces the witness and the public inputs of the scenario, see inputs/README.md.
+--instance replaces the public inputs, once per instance column.
+Values are decimal or 0x hex, with an optional leading -.
+--real proves with KZG (bn256) or IPA (pasta) instead of MockProver.";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let registry = scenario::registry();
+
+    let result = match args.split_first() {
+        None => Ok(run_all(&registry)),
+        Some((command, rest)) => match command.as_str() {
+            "list" => {
+                list(&registry);
+                Ok(true)
+            }
+            "run" => run(&registry, rest, false),
+            "tamper" => run(&registry, rest, true),
+            "analyze" => analyze(&registry, rest),
+            "coverage" => coverage(&registry, rest),
+            "rows" => {
+                rows(&registry, rest.first().map_or("", String::as_str));
+                Ok(true)
+            }
+            "help" | "--help" | "-h" => {
+                println!("{}", USAGE);
+                Ok(true)
+            }
+            _ => Err(format!("unknown command `{}`", command)),
+        },
+    };
+
+    match result {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(err) => {
+            eprintln!("{}\n\n{}", err, USAGE);
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn run_all(registry: &Registry) -> bool {
+    let cache = ParamsCache::default();
+
+    println!("MockProver");
+    let mock = registry.run_all();
+    println!("{}\n", mock);
+
+    // the real prover and verifier, to be sure the forgeries aren't a MockProver artifact
+    println!("Real proofs");
+    let real = registry.prove_all(&cache);
+    println!("{}\n", real);
+
+    // keep every proof the verifier should accept, forgeries included, and check them
+    // again from the files only, that's what other verifiers will get
+    println!("Saved proofs");
+    let artifacts = Path::new("artifacts");
+    let saved: Report = registry
+        .iter()
+        .filter(|s| s.expected() == Verdict::Accept)
+        .map(|s| {
+            let dir = artifacts.join(s.name());
+            if let Err(err) = s.save_proof(&cache, &dir) {
+                eprintln!("can't save {}: {}", s.name(), err);
+            }
+            s.verify_saved(&cache, &dir)
+        })
+        .collect();
+    println!("{}", saved);
+
+    mock.is_success() && real.is_success() && saved.is_success()
+}
+
+fn list(registry: &Registry) {
+    for s in registry.iter() {
+        println!(
+            "{:<32} k={:<3} expected {:?}",
+            s.name(),
+            s.k(),
+            s.expected()
+        );
+    }
+}
+
+fn scenario<'a>(
+    registry: &'a Registry,
+    name: Option<&String>,
+) -> Result<&'a dyn DynScenario, String> {
+    let name = name.ok_or("missing scenario name")?;
+    registry
+        .get(name)
+        .ok_or_else(|| format!("no scenario named `{}`, see `list`", name))
+}
+
+/// The result is printed whatever the verdict: with other inputs,
+/// the expected verdict of the scenario doesn't mean much.
+fn run(registry: &Registry, args: &[String], tamper: bool) -> Result<bool, String> {
+    let scenario = scenario(registry, args.first())?;
+
+    let mut options = RunOptions::default();
+    let mut args = args[1..].iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("missing value after `{}`", arg));
+        match arg.as_str() {
+            "--k" => {
+                let k = value()?;
+                options.k = Some(k.parse().map_err(|_| format!("invalid k `{}`", k))?);
+            }
+            "--input" => options.input = Some(value()?.into()),
+            "--instance" => {
+                let column = value()?
+                    .split(',')
+                    .filter(|v| !v.trim().is_empty())
+                    .map(str::to_string)
+                    .collect();
+                options
+                    .public_inputs
+                    .get_or_insert_with(Vec::new)
+                    .push(column);
+            }
+            "--cell" if tamper => {
+                let cell = value()?;
+                let (target, v) = cell
+                    .rsplit_once('=')
+                    .ok_or(format!("expected region/annotation=value, got `{}`", cell))?;
+                let target: CellTarget = target.parse().map_err(|err| format!("{}", err))?;
+                options.cells.push((target, v.to_string()));
+            }
+            "--real" => options.real = Some(ParamsCache::default()),
+            _ => return Err(format!("unknown option `{}`", arg)),
+        }
+    }
+    if tamper && options.cells.is_empty() {
+        return Err("tamper needs at least one --cell".to_string());
+    }
+
+    let result = scenario.run_with(&options);
+    println!("{}", result);
+    Ok(result.error.is_none())
+}
+
+fn analyze(registry: &Registry, args: &[String]) -> Result<bool, String> {
+    let scenario = scenario(registry, args.first())?;
+    match scenario.analyze() {
+        Ok(findings) => {
+            for finding in findings.iter() {
+                println!("{}", finding);
+            }
+            println!(
+                "{}: {} underconstrained cells",
+                scenario.name(),
+                findings.len()
+            );
+            Ok(true)
+        }
+        Err(err) => {
+            println!("{}: {}", scenario.name(), err);
+            Ok(false)
+        }
+    }
+}
+
+fn coverage(registry: &Registry, args: &[String]) -> Result<bool, String> {
+    let scenario = scenario(registry, args.first())?;
+    match scenario.coverage() {
+        Ok(report) => {
+            println!("{}", report);
+            Ok(true)
+        }
+        Err(err) => {
+            println!("{}: {}", scenario.name(), err);
+            Ok(false)
+        }
+    }
+}
+
+fn rows(registry: &Registry, pattern: &str) {
+    for s in registry.filter(pattern) {
+        match s.row_count() {
+            Ok(count) => println!(
+                "{:<32} {:>6} rows  smallest k={:<3} k={}",
+                s.name(),
+                count.rows,
+                count.k,
+                s.k()
+            ),
+            Err(err) => println!("{:<32} {}", s.name(), err),
+        }
+    }
 }
@@ -0,0 +1,296 @@
+use halo2_poseidon::poseidon::{
+    primitives::{self as poseidon, ConstantLength, Spec},
+    Hash, Pow5Chip, Pow5Config,
+};
+use halo2_proofs::{
+    circuit::{AssignedCell, Layouter},
+    halo2curves::ff::PrimeField,
+    plonk::{Advice, Column, ConstraintSystem, ErrorFront, Expression, Selector},
+    poly::Rotation,
+};
+use std::{fmt::Debug, marker::PhantomData};
+
+/// The hash of a `MerkleCircuit`, in the circuit and natively to build the trees of the scenarios.
+///
+/// Leaves are hashed differently from nodes, so a node can't be passed as a leaf
+/// (see `merkle_nohash2`).
+pub trait MerkleHashGadget<F: PrimeField>: Debug + Clone + Default {
+    type Config: Debug + Clone;
+
+    /// `advice` are the 3 columns of the Merkle circuit, with equality enabled,
+    /// for gadgets that don't need columns of their own.
+    fn configure(meta: &mut ConstraintSystem<F>, advice: [Column<Advice>; 3]) -> Self::Config;
+
+    fn hash_leaf(
+        config: &Self::Config,
+        layouter: impl Layouter<F>,
+        leaf: &AssignedCell<F, F>,
+    ) -> Result<AssignedCell<F, F>, ErrorFront>;
+
+    fn hash_node(
+        config: &Self::Config,
+        layouter: impl Layouter<F>,
+        left: &AssignedCell<F, F>,
+        right: &AssignedCell<F, F>,
+    ) -> Result<AssignedCell<F, F>, ErrorFront>;
+
+    fn native_leaf(leaf: F) -> F;
+
+    fn native_node(left: F, right: F) -> F;
+}
+
+/// The addition "hash" of `merkle_nohash4`: `leaf + leaf` and `left + right`.
+/// Only good to show the bugs of the layout, anyone can find a preimage.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AdderHash;
+
+#[derive(Debug, Clone)]
+pub struct AdderConfig {
+    advice: [Column<Advice>; 3],
+    hash_selector: Selector,
+    leaf_hash_selector: Selector,
+}
+
+impl<F: PrimeField> MerkleHashGadget<F> for AdderHash {
+    type Config = AdderConfig;
+
+    fn configure(meta: &mut ConstraintSystem<F>, advice: [Column<Advice>; 3]) -> AdderConfig {
+        let hash_selector = meta.selector();
+        let leaf_hash_selector = meta.selector();
+
+        meta.create_gate("hash constraint", |meta| {
+            let s = meta.query_selector(hash_selector);
+            let left = meta.query_advice(advice[0], Rotation::cur());
+            let right = meta.query_advice(advice[1], Rotation::cur());
+            let hash = meta.query_advice(advice[2], Rotation::cur());
+            vec![s * (left + right - hash)]
+        });
+        meta.create_gate("leaf hash constraint", |meta| {
+            let s = meta.query_selector(leaf_hash_selector);
+            let leaf = meta.query_advice(advice[0], Rotation::cur());
+            let hash = meta.query_advice(advice[2], Rotation::cur());
+            vec![s * (leaf * Expression::Constant(F::from(2)) - hash)]
+        });
+
+        AdderConfig {
+            advice,
+            hash_selector,
+            leaf_hash_selector,
+        }
+    }
+
+    fn hash_leaf(
+        config: &AdderConfig,
+        mut layouter: impl Layouter<F>,
+        leaf: &AssignedCell<F, F>,
+    ) -> Result<AssignedCell<F, F>, ErrorFront> {
+        layouter.assign_region(
+            || "hash leaf",
+            |mut region| {
+                config.leaf_hash_selector.enable(&mut region, 0)?;
+                leaf.copy_advice(|| "leaf", &mut region, config.advice[0], 0)?;
+                let hash = leaf
+                    .value()
+                    .map(|&leaf| <Self as MerkleHashGadget<F>>::native_leaf(leaf));
+                region.assign_advice(|| "result", config.advice[2], 0, || hash)
+            },
+        )
+    }
+
+    fn hash_node(
+        config: &AdderConfig,
+        mut layouter: impl Layouter<F>,
+        left: &AssignedCell<F, F>,
+        right: &AssignedCell<F, F>,
+    ) -> Result<AssignedCell<F, F>, ErrorFront> {
+        layouter.assign_region(
+            || "hash node",
+            |mut region| {
+                config.hash_selector.enable(&mut region, 0)?;
+                left.copy_advice(|| "left", &mut region, config.advice[0], 0)?;
+                right.copy_advice(|| "right", &mut region, config.advice[1], 0)?;
+                let hash = left
+                    .value()
+                    .zip(right.value())
+                    .map(|(&left, &right)| <Self as MerkleHashGadget<F>>::native_node(left, right));
+                region.assign_advice(|| "result", config.advice[2], 0, || hash)
+            },
+        )
+    }
+
+    fn native_leaf(leaf: F) -> F {
+        leaf + leaf
+    }
+
+    fn native_node(left: F, right: F) -> F {
+        left + right
+    }
+}
+
+/// Tags hashed in front of leaves and nodes, they must differ:
+/// `PoseidonHash` refuses to configure a circuit otherwise.
+pub trait MerkleDomain: Debug {
+    const LEAF_TAG: u64;
+    const NODE_TAG: u64;
+}
+
+/// Leaves are tagged 0 and nodes 1.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultDomain;
+
+impl MerkleDomain for DefaultDomain {
+    const LEAF_TAG: u64 = 0;
+    const NODE_TAG: u64 = 1;
+}
+
+/// Poseidon with the `Pow5Chip`: a leaf is `Poseidon([D::LEAF_TAG, leaf])`
+/// and a node `Poseidon([D::NODE_TAG, left, right])`.
+///
+/// The tags keep a node from passing for a leaf, whatever the length of the message:
+/// the second preimage trick of `merkle_nohash2` and `merkle_nohash3`.
+#[derive(Debug)]
+pub struct PoseidonHash<S, const WIDTH: usize, const RATE: usize, D: MerkleDomain = DefaultDomain>(
+    PhantomData<(S, D)>,
+);
+
+// not derived, which would require `S: Clone + Default`
+impl<S, const WIDTH: usize, const RATE: usize, D: MerkleDomain> Clone
+    for PoseidonHash<S, WIDTH, RATE, D>
+{
+    fn clone(&self) -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<S, const WIDTH: usize, const RATE: usize, D: MerkleDomain> Default
+    for PoseidonHash<S, WIDTH, RATE, D>
+{
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<S, const WIDTH: usize, const RATE: usize, D: MerkleDomain> PoseidonHash<S, WIDTH, RATE, D> {
+    /// Hashes `tag` followed by `message`, the tag is a constant of the circuit.
+    pub(super) fn hash<F: PrimeField, const M: usize, const L: usize>(
+        config: &Pow5Config<F, WIDTH, RATE>,
+        mut layouter: impl Layouter<F>,
+        tag: u64,
+        message: [&AssignedCell<F, F>; M],
+    ) -> Result<AssignedCell<F, F>, ErrorFront>
+    where
+        S: Spec<F, WIDTH, RATE>,
+    {
+        let tag = layouter.assign_region(
+            || "domain tag",
+            |mut region| {
+                region.assign_advice_from_constant(|| "tag", config.state[0], 0, F::from(tag))
+            },
+        )?;
+        let message = std::iter::once(tag)
+            .chain(message.into_iter().cloned())
+            .collect::<Vec<_>>();
+
+        let hasher = Hash::<_, _, S, ConstantLength<L>, WIDTH, RATE>::init(
+            Pow5Chip::construct(config.clone()),
+            layouter.namespace(|| "init"),
+        )?;
+        hasher.hash(
+            layouter.namespace(|| "hash"),
+            message.try_into().map_err(|_| ErrorFront::Synthesis)?,
+        )
+    }
+}
+
+impl<F, S, const WIDTH: usize, const RATE: usize, D> MerkleHashGadget<F>
+    for PoseidonHash<S, WIDTH, RATE, D>
+where
+    F: PrimeField,
+    S: Spec<F, WIDTH, RATE>,
+    D: MerkleDomain,
+{
+    type Config = Pow5Config<F, WIDTH, RATE>;
+
+    fn configure(meta: &mut ConstraintSystem<F>, _: [Column<Advice>; 3]) -> Self::Config {
+        // with the same tag, a node `[left, right]` could pass for a leaf and back
+        assert_ne!(
+            D::LEAF_TAG,
+            D::NODE_TAG,
+            "{} tags leaves and nodes the same",
+            std::any::type_name::<D>()
+        );
+
+        let state = (0..WIDTH).map(|_| meta.advice_column()).collect::<Vec<_>>();
+        let partial_sbox = meta.advice_column();
+
+        let rc_a = (0..WIDTH).map(|_| meta.fixed_column()).collect::<Vec<_>>();
+        let rc_b = (0..WIDTH).map(|_| meta.fixed_column()).collect::<Vec<_>>();
+
+        // also where the tags come from
+        meta.enable_constant(rc_b[0]);
+
+        Pow5Chip::configure::<S>(
+            meta,
+            state.try_into().unwrap(),
+            partial_sbox,
+            rc_a.try_into().unwrap(),
+            rc_b.try_into().unwrap(),
+        )
+    }
+
+    fn hash_leaf(
+        config: &Self::Config,
+        layouter: impl Layouter<F>,
+        leaf: &AssignedCell<F, F>,
+    ) -> Result<AssignedCell<F, F>, ErrorFront> {
+        Self::hash::<F, 1, 2>(config, layouter, D::LEAF_TAG, [leaf])
+    }
+
+    fn hash_node(
+        config: &Self::Config,
+        layouter: impl Layouter<F>,
+        left: &AssignedCell<F, F>,
+        right: &AssignedCell<F, F>,
+    ) -> Result<AssignedCell<F, F>, ErrorFront> {
+        Self::hash::<F, 2, 3>(config, layouter, D::NODE_TAG, [left, right])
+    }
+
+    fn native_leaf(leaf: F) -> F {
+        poseidon::Hash::<_, S, ConstantLength<2>, WIDTH, RATE>::init()
+            .hash([F::from(D::LEAF_TAG), leaf])
+    }
+
+    fn native_node(left: F, right: F) -> F {
+        poseidon::Hash::<_, S, ConstantLength<3>, WIDTH, RATE>::init().hash([
+            F::from(D::NODE_TAG),
+            left,
+            right,
+        ])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_poseidon::poseidon::primitives::P128Pow5T3 as OrchardNullifier;
+    use halo2_proofs::halo2curves::pasta::Fp;
+
+    use super::*;
+
+    #[derive(Debug)]
+    struct SameTags;
+
+    impl MerkleDomain for SameTags {
+        const LEAF_TAG: u64 = 7;
+        const NODE_TAG: u64 = 7;
+    }
+
+    #[test]
+    #[should_panic(expected = "tags leaves and nodes the same")]
+    fn same_tags_do_not_configure() {
+        let mut meta = ConstraintSystem::<Fp>::default();
+        let advice = [(); 3].map(|_| meta.advice_column());
+        <PoseidonHash<OrchardNullifier, 3, 2, SameTags> as MerkleHashGadget<Fp>>::configure(
+            &mut meta, advice,
+        );
+    }
+}
@@ -0,0 +1,55 @@
+use halo2_proofs::{
+    circuit::{Layouter, SimpleFloorPlanner},
+    halo2curves::ff::PrimeField,
+    plonk::{Circuit, ConstraintSystem, ErrorFront},
+};
+use serde::Deserialize;
+
+use super::{
+    gadget::MerkleHashGadget,
+    merkle_circuit::{MerkleCircuit, MerkleConfig},
+};
+
+/// Many leaves of the same tree in one proof: every `MerkleCircuit` shares the columns,
+/// and the hash config, and their roots are all copied to the same instance cell.
+///
+/// The rows grow linearly with the number of proofs and with `DEPTH + 1`,
+/// see `cargo run -- rows merkle_batch` to pick `k`.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(bound = "")]
+pub struct MerkleBatchCircuit<F: PrimeField, H: MerkleHashGadget<F>, const DEPTH: usize> {
+    #[serde(default)]
+    pub proofs: Vec<MerkleCircuit<F, H, DEPTH>>,
+}
+
+impl<F: PrimeField, H: MerkleHashGadget<F>, const DEPTH: usize> Circuit<F>
+    for MerkleBatchCircuit<F, H, DEPTH>
+{
+    type Config = MerkleConfig<F, H>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self {
+            proofs: self.proofs.iter().map(|p| p.without_witnesses()).collect(),
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> MerkleConfig<F, H> {
+        MerkleCircuit::<F, H, DEPTH>::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: MerkleConfig<F, H>,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), ErrorFront> {
+        if self.proofs.is_empty() {
+            return Err(ErrorFront::Synthesis);
+        }
+        for proof in self.proofs.iter() {
+            let root = proof.root(&config, layouter.namespace(|| "proof"))?;
+            layouter.constrain_instance(root.cell(), config.root_hash, 0)?;
+        }
+        Ok(())
+    }
+}
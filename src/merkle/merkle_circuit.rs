@@ -1,70 +1,58 @@
-use halo2_poseidon::poseidon::{
-    primitives::{ConstantLength, Spec},
-    Hash, Pow5Chip, Pow5Config,
-};
 use halo2_proofs::{
-    arithmetic::Field,
     circuit::{AssignedCell, Layouter, SimpleFloorPlanner, Value},
-    halo2curves::pasta::Fp,
+    halo2curves::ff::PrimeField,
     plonk::{
         Advice, Circuit, Column, ConstraintSystem, ErrorFront, Expression, Instance, Selector,
     },
     poly::Rotation,
 };
+use serde::Deserialize;
 use std::marker::PhantomData;
 
+use super::gadget::MerkleHashGadget;
+
 #[derive(Debug, Clone)]
-pub struct MerkleConfig<F: Field, const WIDTH: usize, const RATE: usize> {
-    pub pow5config: Pow5Config<F, WIDTH, RATE>,
+pub struct MerkleConfig<F: PrimeField, H: MerkleHashGadget<F>> {
+    pub hash: H::Config,
     pub merkle: [Column<Advice>; 3],
     pub swap_selector: Selector,
     pub swap_bit_bool_selector: Selector,
     pub root_hash: Column<Instance>,
 }
 
-#[derive(Debug, Default, Clone)]
-pub struct MerkleCircuit<S: Spec<Fp, WIDTH, RATE>, const WIDTH: usize, const RATE: usize> {
-    pub leaf: Value<Fp>,
-    pub path_elements: Vec<Value<Fp>>,
-    pub path_indices: Vec<Value<Fp>>,
-    pub _spec: PhantomData<S>,
+/// The swap and bool gates of `merkle_nohash4`, with any hash.
+///
+/// The depth is part of the circuit, not of the witness: a shorter path is another
+/// circuit with other keys (see `merkle_nohash1`), and paths of the wrong length
+/// don't synthesize.
+#[derive(Debug, Default, Clone, Deserialize)]
+pub struct MerkleCircuit<F: PrimeField, H: MerkleHashGadget<F>, const DEPTH: usize> {
+    #[serde(default, deserialize_with = "crate::inputs::value")]
+    pub leaf: Value<F>,
+    #[serde(default, deserialize_with = "crate::inputs::values")]
+    pub path_elements: Vec<Value<F>>,
+    #[serde(default, deserialize_with = "crate::inputs::values")]
+    pub path_indices: Vec<Value<F>>,
+    #[serde(skip)]
+    pub _hash: PhantomData<H>,
 }
 
-impl<S: Spec<Fp, WIDTH, RATE>, const WIDTH: usize, const RATE: usize> Circuit<Fp>
-    for MerkleCircuit<S, WIDTH, RATE>
+impl<F: PrimeField, H: MerkleHashGadget<F>, const DEPTH: usize> Circuit<F>
+    for MerkleCircuit<F, H, DEPTH>
 {
-    type Config = MerkleConfig<Fp, WIDTH, RATE>;
+    type Config = MerkleConfig<F, H>;
     type FloorPlanner = SimpleFloorPlanner;
 
     fn without_witnesses(&self) -> Self {
         Self {
             leaf: Value::unknown(),
-            path_elements: vec![],
-            path_indices: vec![],
-            _spec: PhantomData,
+            path_elements: vec![Value::unknown(); DEPTH],
+            path_indices: vec![Value::unknown(); DEPTH],
+            _hash: PhantomData,
         }
     }
 
-    fn configure(meta: &mut ConstraintSystem<Fp>) -> MerkleConfig<Fp, WIDTH, RATE> {
-        let state = (0..WIDTH).map(|_| meta.advice_column()).collect::<Vec<_>>();
-        let partial_sbox = meta.advice_column();
-
-        let rc_a = (0..WIDTH).map(|_| meta.fixed_column()).collect::<Vec<_>>();
-        let rc_b = (0..WIDTH).map(|_| meta.fixed_column()).collect::<Vec<_>>();
-
-        meta.enable_constant(rc_b[0]);
-
-        let pow5config = Pow5Chip::configure::<S>(
-            meta,
-            state.try_into().unwrap(),
-            partial_sbox,
-            rc_a.try_into().unwrap(),
-            rc_b.try_into().unwrap(),
-        );
-
-        let root_hash = meta.instance_column();
-        meta.enable_equality(root_hash);
-
+    fn configure(meta: &mut ConstraintSystem<F>) -> MerkleConfig<F, H> {
         let advice = [
             meta.advice_column(),
             meta.advice_column(),
@@ -74,13 +62,16 @@ impl<S: Spec<Fp, WIDTH, RATE>, const WIDTH: usize, const RATE: usize> Circuit<Fp
         meta.enable_equality(advice[1]);
         meta.enable_equality(advice[2]);
 
+        let root_hash = meta.instance_column();
+        meta.enable_equality(root_hash);
+
         let swap_selector = meta.selector();
         let swap_bit_bool_selector = meta.selector();
 
         meta.create_gate("bool constraint", |meta| {
             let s = meta.query_selector(swap_bit_bool_selector);
             let swap_bit = meta.query_advice(advice[2], Rotation::cur());
-            vec![s * swap_bit.clone() * (Expression::Constant(Fp::from(1)) - swap_bit)]
+            vec![s * swap_bit.clone() * (Expression::Constant(F::ONE) - swap_bit)]
         });
 
         meta.create_gate("swap constraint", |meta| {
@@ -102,7 +93,7 @@ impl<S: Spec<Fp, WIDTH, RATE>, const WIDTH: usize, const RATE: usize> Circuit<Fp
         });
 
         MerkleConfig {
-            pow5config,
+            hash: H::configure(meta, advice),
             merkle: advice,
             swap_selector,
             swap_bit_bool_selector,
@@ -112,51 +103,69 @@ impl<S: Spec<Fp, WIDTH, RATE>, const WIDTH: usize, const RATE: usize> Circuit<Fp
 
     fn synthesize(
         &self,
-        config: MerkleConfig<Fp, WIDTH, RATE>,
-        mut layouter: impl Layouter<Fp>,
+        config: MerkleConfig<F, H>,
+        mut layouter: impl Layouter<F>,
     ) -> Result<(), ErrorFront> {
-        let chip = Pow5Chip::construct(config.pow5config.clone());
+        let root = self.root(&config, layouter.namespace(|| "root"))?;
+        layouter.constrain_instance(root.cell(), config.root_hash, 0)
+    }
+}
 
+impl<F: PrimeField, H: MerkleHashGadget<F>, const DEPTH: usize> MerkleCircuit<F, H, DEPTH> {
+    /// Hashes the leaf up the path, without exposing the root.
+    pub fn root(
+        &self,
+        config: &MerkleConfig<F, H>,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<AssignedCell<F, F>, ErrorFront> {
         let leaf_cell = layouter.assign_region(
             || "assign leaf",
             |mut region| region.assign_advice(|| "assign leaf", config.merkle[0], 0, || self.leaf),
         )?;
 
-        let mut digest: AssignedCell<Fp, Fp> = leaf_cell;
-        for i in 0..self.path_elements.len() {
-            digest = self.merkle_prove_layer(
-                config.clone(),
-                chip.clone(),
+        let (root, _) = self.root_of(config, layouter, &leaf_cell)?;
+        Ok(root)
+    }
+
+    /// Same as `root` for a leaf computed elsewhere in the circuit, `self.leaf` is ignored.
+    /// Also returns the swap bits, from the leaf to the root.
+    pub fn root_of(
+        &self,
+        config: &MerkleConfig<F, H>,
+        mut layouter: impl Layouter<F>,
+        leaf: &AssignedCell<F, F>,
+    ) -> Result<(AssignedCell<F, F>, Vec<AssignedCell<F, F>>), ErrorFront> {
+        if self.path_elements.len() != DEPTH || self.path_indices.len() != DEPTH {
+            return Err(ErrorFront::Synthesis);
+        }
+
+        let mut digest = H::hash_leaf(&config.hash, layouter.namespace(|| "hash leaf"), leaf)?;
+        let mut bits = Vec::with_capacity(DEPTH);
+        for i in 0..DEPTH {
+            let (parent, bit) = self.merkle_prove_layer(
+                config,
                 layouter.namespace(|| "prove tree"),
                 &digest,
                 self.path_elements[i],
                 self.path_indices[i],
             )?;
+            digest = parent;
+            bits.push(bit);
         }
 
-        layouter.constrain_instance(digest.cell(), config.root_hash, 0)?;
-        Ok(())
+        Ok((digest, bits))
     }
-}
 
-impl<S: Spec<Fp, WIDTH, RATE>, const WIDTH: usize, const RATE: usize>
-    MerkleCircuit<S, WIDTH, RATE>
-{
+    /// The parent of `node_cell`, and the cell of its swap bit.
     pub fn merkle_prove_layer(
         &self,
-        config: MerkleConfig<Fp, WIDTH, RATE>,
-        hash_chip: Pow5Chip<Fp, WIDTH, RATE>,
-        mut layouter: impl Layouter<Fp>,
-        node_cell: &AssignedCell<Fp, Fp>,
-        neighbor: Value<Fp>,
-        swap_bit: Value<Fp>,
-    ) -> Result<AssignedCell<Fp, Fp>, ErrorFront> {
-        let hasher = Hash::<_, _, S, ConstantLength<2>, WIDTH, RATE>::init(
-            hash_chip,
-            layouter.namespace(|| "init"),
-        )?;
-
-        let (left, right) = layouter.assign_region(
+        config: &MerkleConfig<F, H>,
+        mut layouter: impl Layouter<F>,
+        node_cell: &AssignedCell<F, F>,
+        neighbor: Value<F>,
+        swap_bit: Value<F>,
+    ) -> Result<(AssignedCell<F, F>, AssignedCell<F, F>), ErrorFront> {
+        let (left, right, bit) = layouter.assign_region(
             || "merkle prove",
             |mut region| {
                 config.swap_selector.enable(&mut region, 0)?;
@@ -169,12 +178,13 @@ impl<S: Spec<Fp, WIDTH, RATE>, const WIDTH: usize, const RATE: usize>
                     0,
                 )?;
                 region.assign_advice(|| "set neighbor node", config.merkle[1], 0, || neighbor)?;
-                region.assign_advice(|| "set swap bit", config.merkle[2], 0, || swap_bit)?;
+                let bit =
+                    region.assign_advice(|| "set swap bit", config.merkle[2], 0, || swap_bit)?;
 
                 let mut left = node_cell.value().cloned();
                 let mut right = neighbor;
                 swap_bit.map(|f| {
-                    (left, right) = if f == Fp::ZERO {
+                    (left, right) = if f == F::ZERO {
                         (left, right)
                     } else {
                         (right, left)
@@ -194,11 +204,41 @@ impl<S: Spec<Fp, WIDTH, RATE>, const WIDTH: usize, const RATE: usize>
                     || right,
                 )?;
 
-                Ok((left_cell, right_cell))
+                Ok((left_cell, right_cell, bit))
             },
         )?;
 
-        let result_hash_cell = hasher.hash(layouter.namespace(|| "hash row"), [left, right])?;
-        Ok(result_hash_cell)
+        let digest = H::hash_node(
+            &config.hash,
+            layouter.namespace(|| "hash row"),
+            &left,
+            &right,
+        )?;
+        Ok((digest, bit))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::{dev::MockProver, halo2curves::pasta::Fp};
+
+    use super::*;
+    use crate::merkle::{AdderHash, MerkleTree, Padding};
+
+    #[test]
+    fn short_path_does_not_synthesize() {
+        let leaves = [1, 2, 3, 4].map(Fp::from);
+        let tree = MerkleTree::<Fp, AdderHash>::new(&leaves, 2, Padding::Zero).unwrap();
+        let (path_elements, path_indices) = tree.path(0).unwrap();
+
+        // the path of a tree of depth 1: the first node as a leaf, and its sibling
+        let circuit = MerkleCircuit::<Fp, AdderHash, 2> {
+            leaf: Value::known(tree.node(1, 0).unwrap()),
+            path_elements: vec![Value::known(path_elements[1])],
+            path_indices: vec![Value::known(path_indices[1])],
+            _hash: PhantomData,
+        };
+        let result = MockProver::run(4, &circuit, vec![vec![tree.root()]]);
+        assert!(matches!(result, Err(ErrorFront::Synthesis)));
     }
 }
@@ -8,6 +8,7 @@ use halo2_proofs::{
     },
     poly::Rotation,
 };
+use serde::Deserialize;
 
 #[derive(Debug, Clone)]
 pub struct MerkleConfig {
@@ -19,10 +20,13 @@ pub struct MerkleConfig {
     pub root_hash: Column<Instance>,
 }
 
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, Deserialize)]
 pub struct MerkleCircuitNoHash4 {
+    #[serde(default, deserialize_with = "crate::inputs::value")]
     pub leaf: Value<Fp>,
+    #[serde(default, deserialize_with = "crate::inputs::values")]
     pub path_elements: Vec<Value<Fp>>,
+    #[serde(default, deserialize_with = "crate::inputs::values")]
     pub path_indices: Vec<Value<Fp>>,
 }
 
@@ -1,24 +1,55 @@
-use halo2_poseidon::poseidon::primitives::{ConstantLength, Hash, P128Pow5T3 as OrchardNullifier};
+use halo2_poseidon::poseidon::primitives::P128Pow5T3 as OrchardNullifier;
 use halo2_proofs::{
     circuit::Value,
-    dev::{CellValue, MockProver},
-    halo2curves::pasta::pallas::Base as PallasFp,
+    halo2curves::{bn256::Fr, pasta::pallas::Base as PallasFp},
 };
 use std::marker::PhantomData;
 
-mod merkle_nohash0;
-mod merkle_nohash1;
-mod merkle_nohash2;
-mod merkle_nohash3;
-mod merkle_nohash4;
+use crate::{
+    hash::spec::{P128Pow5T3Bn256, P128Pow5T5Bn256},
+    layout::Layout,
+    prove::Backend,
+    scenario::{CircuitScenario, Registry, Verdict},
+    tamper::{CellTarget, Tamper},
+};
+
+pub mod gadget;
+pub mod merkle_batch;
+pub mod merkle_circuit;
+pub mod merkle_nohash0;
+pub mod merkle_nohash1;
+pub mod merkle_nohash2;
+pub mod merkle_nohash3;
+pub mod merkle_nohash4;
+pub mod nullifier;
+pub mod tree;
 
+pub use gadget::{AdderHash, DefaultDomain, MerkleDomain, MerkleHashGadget, PoseidonHash};
+pub use merkle_batch::MerkleBatchCircuit;
+pub use merkle_circuit::MerkleCircuit;
 pub use merkle_nohash0::MerkleCircuitNoHash0;
 pub use merkle_nohash1::MerkleCircuitNoHash1;
 pub use merkle_nohash2::MerkleCircuitNoHash2;
 pub use merkle_nohash3::MerkleCircuitNoHash3;
 pub use merkle_nohash4::MerkleCircuitNoHash4;
+pub use nullifier::NullifierCircuit;
+pub use tree::{MerkleTree, Padding, TreeError};
+
+pub fn register(registry: &mut Registry) {
+    merke_nohash0(registry);
+    merke_nohash1(registry);
+    merke_nohash2(registry);
+    merke_nohash3(registry);
+    merke_nohash4(registry);
+    merke_circuit_with_hash(registry);
+    merkle_circuit_large_tree(registry);
+    merkle_batch::<2>(registry, ["merkle_batch/1-depth2", "merkle_batch/4-depth2"]);
+    merkle_batch::<6>(registry, ["merkle_batch/1-depth6", "merkle_batch/4-depth6"]);
+    merkle_batch_forged(registry);
+    nullifier(registry);
+}
 
-pub fn merke_nohash0() {
+fn merke_nohash0(registry: &mut Registry) {
     // let's use 4 leaves for the example
     let leaves = [
         PallasFp::from(2),
@@ -42,11 +73,16 @@ pub fn merke_nohash0() {
         ],
     };
 
-    let prover = MockProver::run(4, &circuit, vec![vec![root]]).unwrap();
-    assert!(prover.verify().is_ok());
+    registry.register(CircuitScenario::new(
+        "merkle_nohash0/honest",
+        circuit,
+        4,
+        vec![vec![root]],
+        Verdict::Accept,
+    ));
 }
 
-pub fn merke_nohash1() {
+fn merke_nohash1(registry: &mut Registry) {
     let leaves = [
         PallasFp::from(2),
         PallasFp::from(5),
@@ -66,8 +102,13 @@ pub fn merke_nohash1() {
         ],
     };
 
-    let prover = MockProver::run(4, &circuit, vec![vec![root]]).unwrap();
-    assert!(prover.verify().is_ok());
+    registry.register(CircuitScenario::new(
+        "merkle_nohash1/honest",
+        circuit,
+        4,
+        vec![vec![root]],
+        Verdict::Accept,
+    ));
 
     // Let's fake the proof
     // we just pass the root hash as the leaf, and no other layer
@@ -76,11 +117,16 @@ pub fn merke_nohash1() {
         path_elements: vec![],
         path_indices: vec![],
     };
-    let prover = MockProver::run(4, &circuit, vec![vec![root]]).unwrap();
-    assert!(prover.verify().is_ok());
+    registry.register(CircuitScenario::new(
+        "merkle_nohash1/root-as-leaf",
+        circuit,
+        4,
+        vec![vec![root]],
+        Verdict::Accept,
+    ));
 }
 
-pub fn merke_nohash2() {
+fn merke_nohash2(registry: &mut Registry) {
     let leaves = [
         PallasFp::from(2),
         PallasFp::from(5),
@@ -97,11 +143,16 @@ pub fn merke_nohash2() {
         path_indices: vec![Value::known(PallasFp::from(0))],
     };
 
-    let prover = MockProver::run(4, &circuit, vec![vec![root]]).unwrap();
-    assert!(prover.verify().is_ok());
+    registry.register(CircuitScenario::new(
+        "merkle_nohash2/node-as-leaf",
+        circuit,
+        4,
+        vec![vec![root]],
+        Verdict::Accept,
+    ));
 }
 
-pub fn merke_nohash3() {
+fn merke_nohash3(registry: &mut Registry) {
     let hash_leaf = |v: PallasFp| v + v;
     let leaves = [
         PallasFp::from(2),
@@ -121,8 +172,13 @@ pub fn merke_nohash3() {
             Value::known(PallasFp::from(0)),
         ],
     };
-    let prover = MockProver::run(4, &circuit, vec![vec![root]]).unwrap();
-    assert!(prover.verify().is_ok());
+    registry.register(CircuitScenario::new(
+        "merkle_nohash3/honest",
+        circuit,
+        4,
+        vec![vec![root]],
+        Verdict::Accept,
+    ));
 
     // you know the drill by now... let's fake the proof again!
     let random_leaf = PallasFp::from(15);
@@ -131,19 +187,33 @@ pub fn merke_nohash3() {
         path_elements: vec![Value::known(h2)],
         path_indices: vec![Value::known(PallasFp::from(0))],
     };
-    let mut prover = MockProver::run(4, &circuit, vec![vec![root]]).unwrap();
-    // Ok this one is a bit tricky, we need to change the advice for the first and third columns
-    // because the value is copied multiple times
-    let advice0 = prover.advice_mut(0);
-    advice0[1] = CellValue::Assigned(h1);
-    advice0[2] = CellValue::Assigned(h1);
-    let advice2 = prover.advice_mut(2);
-    advice2[0] = CellValue::Assigned(h1);
-    advice2[2] = CellValue::Assigned(root);
-    assert!(prover.verify().is_ok());
+    registry.register(
+        CircuitScenario::new(
+            "merkle_nohash3/forged-leaf",
+            circuit,
+            4,
+            vec![vec![root]],
+            Verdict::Accept,
+        )
+        // Ok this one is a bit tricky, we need to change the leaf hash and every cell it is copied to,
+        // then the result of the only layer
+        .with_tamper(
+            Tamper::new()
+                .set(CellTarget::new("assign leaf", "assign leaf hash"), h1)
+                .set(
+                    CellTarget::new("merkle prove", "copy previous node cell"),
+                    h1,
+                )
+                .set(
+                    CellTarget::new("merkle prove", "left node to be hashed"),
+                    h1,
+                )
+                .set(CellTarget::new("merkle prove", "result"), root),
+        ),
+    );
 }
 
-pub fn merke_nohash4() {
+fn merke_nohash4(registry: &mut Registry) {
     let hash_leaf = |v: PallasFp| v + v;
     let leaves = [
         PallasFp::from(2),
@@ -163,36 +233,295 @@ pub fn merke_nohash4() {
             Value::known(PallasFp::from(0)),
         ],
     };
-    let prover = MockProver::run(4, &circuit, vec![vec![root]]).unwrap();
-    assert!(prover.verify().is_ok());
+    registry.register(CircuitScenario::new(
+        "merkle_nohash4/honest",
+        circuit,
+        4,
+        vec![vec![root]],
+        Verdict::Accept,
+    ));
 }
 
-mod merkle_circuit;
-use merkle_circuit::MerkleCircuit;
+fn merke_circuit_with_hash(registry: &mut Registry) {
+    merkle_circuit::<PallasFp, PoseidonHash<OrchardNullifier, 3, 2>>(
+        registry,
+        ["merkle_circuit/honest", "merkle_circuit/node-as-leaf"],
+        10,
+    );
+    // over bn256, for the KZG verifiers
+    merkle_circuit::<Fr, PoseidonHash<P128Pow5T3Bn256, 3, 2>>(
+        registry,
+        [
+            "merkle_circuit_bn256/honest",
+            "merkle_circuit_bn256/node-as-leaf",
+        ],
+        10,
+    );
+    merkle_circuit::<Fr, PoseidonHash<P128Pow5T5Bn256, 5, 4>>(
+        registry,
+        [
+            "merkle_circuit_bn256_width5/honest",
+            "merkle_circuit_bn256_width5/node-as-leaf",
+        ],
+        10,
+    );
+    // the same gates as `merkle_nohash4`, with the toy hash
+    merkle_circuit::<PallasFp, AdderHash>(
+        registry,
+        [
+            "merkle_circuit_adder/honest",
+            "merkle_circuit_adder/node-as-leaf",
+        ],
+        4,
+    );
+}
 
-pub fn merke_circuit_with_hash() {
-    let leaves = [
-        PallasFp::from(1),
-        PallasFp::from(2),
-        PallasFp::from(3),
-        PallasFp::from(4),
-    ];
-    let h1 =
-        Hash::<_, OrchardNullifier, ConstantLength<2>, 3, 2>::init().hash([leaves[0], leaves[1]]);
-    let h2 =
-        Hash::<_, OrchardNullifier, ConstantLength<2>, 3, 2>::init().hash([leaves[2], leaves[3]]);
-    let root = Hash::<_, OrchardNullifier, ConstantLength<2>, 3, 2>::init().hash([h1, h2]);
+/// `names` of the honest and the node-as-leaf scenarios
+fn merkle_circuit<F: Backend, H: MerkleHashGadget<F> + 'static>(
+    registry: &mut Registry,
+    names: [&'static str; 2],
+    k: u32,
+) {
+    let leaves = [F::from(1), F::from(2), F::from(3), F::from(4)];
+    let tree = MerkleTree::<F, H>::new(&leaves, 2, Padding::Zero).unwrap();
+    let root = tree.root();
 
-    let circuit = MerkleCircuit::<OrchardNullifier, 3, 2> {
-        leaf: Value::known(leaves[0]),
-        path_elements: vec![Value::known(leaves[1]), Value::known(h2)],
-        path_indices: vec![
-            Value::known(PallasFp::from(0)),
-            Value::known(PallasFp::from(0)),
+    registry.register(CircuitScenario::new(
+        names[0],
+        tree.circuit::<2>(0).unwrap(),
+        k,
+        vec![vec![root]],
+        Verdict::Accept,
+    ));
+
+    // the attack of `merkle_nohash2` doesn't work anymore, the leaf is hashed.
+    // It needs a path of length 1, which is an error with a depth of 2,
+    // so we even give it a circuit of depth 1
+    let circuit = MerkleCircuit::<F, H, 1> {
+        leaf: Value::known(tree.node(1, 0).unwrap()),
+        path_elements: vec![Value::known(tree.node(1, 1).unwrap())],
+        path_indices: vec![Value::known(F::ZERO)],
+        _hash: PhantomData,
+    };
+    registry.register(CircuitScenario::new(
+        names[1],
+        circuit,
+        k,
+        vec![vec![root]],
+        Verdict::Reject,
+    ));
+}
+
+fn merkle_circuit_large_tree(registry: &mut Registry) {
+    // 1000 leaves, padded to 1024 with copies of the last one
+    let leaves = (0..1000u64).map(PallasFp::from).collect::<Vec<_>>();
+    let tree = MerkleTree::<PallasFp, PoseidonHash<OrchardNullifier, 3, 2>>::new(
+        &leaves,
+        10,
+        Padding::RepeatLast,
+    )
+    .unwrap();
+
+    registry.register(CircuitScenario::new(
+        "merkle_circuit/large-tree",
+        tree.circuit::<10>(777).unwrap(),
+        11,
+        vec![vec![tree.root()]],
+        Verdict::Accept,
+    ));
+
+    // a padding leaf is in the tree too
+    registry.register(CircuitScenario::new(
+        "merkle_circuit/large-tree-padding",
+        tree.circuit::<10>(1023).unwrap(),
+        11,
+        vec![vec![tree.root()]],
+        Verdict::Accept,
+    ));
+}
+
+/// `names` of the batches of 1 and 4 leaves, `k` is as small as the circuit allows
+fn merkle_batch<const DEPTH: usize>(registry: &mut Registry, names: [&'static str; 2]) {
+    let leaves = (0..1u64 << DEPTH).map(PallasFp::from).collect::<Vec<_>>();
+    let tree = MerkleTree::<PallasFp, PoseidonHash<OrchardNullifier, 3, 2>>::new(
+        &leaves,
+        DEPTH,
+        Padding::Zero,
+    )
+    .unwrap();
+
+    for (name, n) in names.into_iter().zip([1, 4]) {
+        let circuit = MerkleBatchCircuit {
+            proofs: (0..n)
+                .map(|i| tree.circuit::<DEPTH>(i * 3 % leaves.len()).unwrap())
+                .collect(),
+        };
+        let k = Layout::<PallasFp>::row_count(&circuit).unwrap().k;
+        registry.register(CircuitScenario::new(
+            name,
+            circuit,
+            k,
+            vec![vec![tree.root()]],
+            Verdict::Accept,
+        ));
+    }
+}
+
+fn merkle_batch_forged(registry: &mut Registry) {
+    let leaves = (0..4u64).map(PallasFp::from).collect::<Vec<_>>();
+    let tree = MerkleTree::<PallasFp, PoseidonHash<OrchardNullifier, 3, 2>>::new(
+        &leaves,
+        2,
+        Padding::Zero,
+    )
+    .unwrap();
+    let other = MerkleTree::<PallasFp, PoseidonHash<OrchardNullifier, 3, 2>>::new(
+        &[PallasFp::from(42)],
+        2,
+        Padding::Zero,
+    )
+    .unwrap();
+
+    // every root is copied to the same instance cell, one leaf of another tree is enough
+    let circuit = MerkleBatchCircuit {
+        proofs: vec![
+            tree.circuit::<2>(0).unwrap(),
+            other.circuit::<2>(0).unwrap(),
+            tree.circuit::<2>(3).unwrap(),
         ],
-        _spec: PhantomData,
     };
+    let k = Layout::<PallasFp>::row_count(&circuit).unwrap().k;
+    registry.register(CircuitScenario::new(
+        "merkle_batch/leaf-of-another-tree",
+        circuit,
+        k,
+        vec![vec![tree.root()]],
+        Verdict::Reject,
+    ));
+}
 
-    let prover = MockProver::run(10, &circuit, vec![vec![root]]).unwrap();
-    assert!(prover.verify().is_ok());
+fn nullifier(registry: &mut Registry) {
+    use nullifier::{native_commitment, native_nullifier};
+    type Spend = NullifierCircuit<PallasFp, OrchardNullifier, 3, 2, 2>;
+    type Tree = MerkleTree<PallasFp, PoseidonHash<OrchardNullifier, 3, 2>>;
+    let commitment = native_commitment::<_, OrchardNullifier, 3, 2>;
+    let nullifier = native_nullifier::<_, OrchardNullifier, 3, 2>;
+
+    // 4 notes, `(secret, nk)`
+    let notes = (0..4u64)
+        .map(|i| (PallasFp::from(100 + i), PallasFp::from(200 + i)))
+        .collect::<Vec<_>>();
+    let commitments = notes
+        .iter()
+        .map(|&(secret, nk)| commitment(secret, nk))
+        .collect::<Vec<_>>();
+    let tree = Tree::new(&commitments, 2, Padding::Zero).unwrap();
+    let spend_in = |tree: &Tree, (secret, nk): (PallasFp, PallasFp), index: usize| {
+        let (path_elements, path_indices) = tree.path(index).unwrap();
+        Spend {
+            secret: Value::known(secret),
+            nk: Value::known(nk),
+            path_elements: path_elements.into_iter().map(Value::known).collect(),
+            path_indices: path_indices.into_iter().map(Value::known).collect(),
+            _spec: PhantomData,
+        }
+    };
+    let spend = |note, index| spend_in(&tree, note, index);
+    let k = Layout::<PallasFp>::row_count(&spend(notes[2], 2))
+        .unwrap()
+        .k;
+
+    registry.register(CircuitScenario::new(
+        "nullifier/honest",
+        spend(notes[2], 2),
+        k,
+        vec![vec![tree.root(), nullifier(notes[2].1, 2)]],
+        Verdict::Accept,
+    ));
+
+    // spending note 2 again with a fresh nullifier, the one of another leaf:
+    // the index comes from the path, not from the prover
+    registry.register(CircuitScenario::new(
+        "nullifier/reused-note",
+        spend(notes[2], 2),
+        k,
+        vec![vec![tree.root(), nullifier(notes[2].1, 3)]],
+        Verdict::Reject,
+    ));
+
+    // the nullifier of a note already spent, to block its owner
+    registry.register(CircuitScenario::new(
+        "nullifier/nullifier-of-another-note",
+        spend(notes[2], 2),
+        k,
+        vec![vec![tree.root(), nullifier(notes[0].1, 0)]],
+        Verdict::Reject,
+    ));
+
+    // a nullifier key of our own: the commitment isn't in the tree anymore
+    let forged = (notes[2].0, PallasFp::from(42));
+    registry.register(CircuitScenario::new(
+        "nullifier/forged-key",
+        spend(forged, 2),
+        k,
+        vec![vec![tree.root(), nullifier(forged.1, 2)]],
+        Verdict::Reject,
+    ));
+
+    // note 2 in the tree a second time, at leaf 3: the path of leaf 3 is consistent
+    // with its bits and its nullifier, only the root gives it away
+    let duplicated = [
+        commitments[0],
+        commitments[1],
+        commitments[2],
+        commitments[2],
+    ];
+    let duplicated = Tree::new(&duplicated, 2, Padding::Zero).unwrap();
+    registry.register(CircuitScenario::new(
+        "nullifier/path-of-another-leaf",
+        spend_in(&duplicated, notes[2], 3),
+        k,
+        vec![vec![tree.root(), nullifier(notes[2].1, 3)]],
+        Verdict::Reject,
+    ));
+
+    // in a tree which does hold it twice, the same note has a second nullifier:
+    // the nullifier is bound to the leaf, keeping commitments unique is up to the tree
+    registry.register(CircuitScenario::new(
+        "nullifier/duplicate-commitment",
+        spend_in(&duplicated, notes[2], 3),
+        k,
+        vec![vec![duplicated.root(), nullifier(notes[2].1, 3)]],
+        Verdict::Accept,
+    ));
+
+    // the same spend over bn256
+    let (secret, nk) = (Fr::from(102), Fr::from(202));
+    let commitment = native_commitment::<_, P128Pow5T3Bn256, 3, 2>(secret, nk);
+    let tree = MerkleTree::<Fr, PoseidonHash<P128Pow5T3Bn256, 3, 2>>::new(
+        &[Fr::from(0), Fr::from(1), commitment],
+        2,
+        Padding::Zero,
+    )
+    .unwrap();
+    let (path_elements, path_indices) = tree.path(2).unwrap();
+    let circuit = NullifierCircuit::<Fr, P128Pow5T3Bn256, 3, 2, 2> {
+        secret: Value::known(secret),
+        nk: Value::known(nk),
+        path_elements: path_elements.into_iter().map(Value::known).collect(),
+        path_indices: path_indices.into_iter().map(Value::known).collect(),
+        _spec: PhantomData,
+    };
+    // 57 partial rounds instead of 56: not the rows of the pasta circuit
+    let k = Layout::<Fr>::row_count(&circuit).unwrap().k;
+    registry.register(CircuitScenario::new(
+        "nullifier/honest-bn256",
+        circuit,
+        k,
+        vec![vec![
+            tree.root(),
+            native_nullifier::<_, P128Pow5T3Bn256, 3, 2>(nk, 2),
+        ]],
+        Verdict::Accept,
+    ));
 }
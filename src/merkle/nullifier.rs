@@ -0,0 +1,251 @@
+//! Note commitments and nullifiers, the private membership of Zcash.
+//!
+//! A note is a `secret` and a nullifier key `nk`, its commitment
+//! `Poseidon([COMMITMENT_TAG, secret, nk])` is a leaf of a Merkle tree. Spending the note
+//! proves the commitment is in the tree, without saying which leaf it is, and reveals its
+//! nullifier `Poseidon([NULLIFIER_TAG, nk, index])`: the same note can't be spent twice,
+//! the verifier would see the same nullifier again.
+//!
+//! `index` is not a witness of its own, it is rebuilt from the swap bits of the path,
+//! so the nullifier of a leaf is the only one the prover can reveal. The nullifier is
+//! bound to the leaf, not to the note: the tree must not hold the same commitment twice.
+
+use halo2_poseidon::poseidon::primitives::{self as poseidon, ConstantLength, Spec};
+use halo2_proofs::{
+    circuit::{AssignedCell, Layouter, SimpleFloorPlanner, Value},
+    halo2curves::ff::PrimeField,
+    plonk::{Circuit, ConstraintSystem, ErrorFront, Expression, Selector},
+    poly::Rotation,
+};
+use serde::Deserialize;
+use std::marker::PhantomData;
+
+use super::{
+    gadget::{MerkleHashGadget, PoseidonHash},
+    merkle_circuit::{MerkleCircuit, MerkleConfig},
+};
+
+/// Tags of the commitment and nullifier hashes, after the leaf and node tags of
+/// `DefaultDomain`: no digest of the circuit can pass for another kind of digest.
+pub const COMMITMENT_TAG: u64 = 2;
+pub const NULLIFIER_TAG: u64 = 3;
+
+#[derive(Debug, Clone)]
+pub struct NullifierConfig<F: PrimeField, H: MerkleHashGadget<F>> {
+    /// the root on instance row 0, the nullifier on row 1
+    pub merkle: MerkleConfig<F, H>,
+    q_index: Selector,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+pub struct NullifierCircuit<
+    F: PrimeField,
+    S: Spec<F, WIDTH, RATE>,
+    const WIDTH: usize,
+    const RATE: usize,
+    const DEPTH: usize,
+> {
+    #[serde(default, deserialize_with = "crate::inputs::value")]
+    pub secret: Value<F>,
+    #[serde(default, deserialize_with = "crate::inputs::value")]
+    pub nk: Value<F>,
+    #[serde(default, deserialize_with = "crate::inputs::values")]
+    pub path_elements: Vec<Value<F>>,
+    #[serde(default, deserialize_with = "crate::inputs::values")]
+    pub path_indices: Vec<Value<F>>,
+    #[serde(skip)]
+    pub _spec: PhantomData<S>,
+}
+
+type PoseidonNullifierConfig<F, S, const WIDTH: usize, const RATE: usize> =
+    NullifierConfig<F, PoseidonHash<S, WIDTH, RATE>>;
+
+impl<F, S, const WIDTH: usize, const RATE: usize, const DEPTH: usize>
+    NullifierCircuit<F, S, WIDTH, RATE, DEPTH>
+where
+    F: PrimeField,
+    S: Spec<F, WIDTH, RATE>,
+{
+    /// `index = sum(bit_i * 2^i)`, from the root down: `index' = 2 * index + bit`
+    fn leaf_index(
+        config: &PoseidonNullifierConfig<F, S, WIDTH, RATE>,
+        mut layouter: impl Layouter<F>,
+        bits: &[AssignedCell<F, F>],
+    ) -> Result<AssignedCell<F, F>, ErrorFront> {
+        let [index_column, bit_column, _] = config.merkle.merkle;
+        layouter.assign_region(
+            || "leaf index",
+            |mut region| {
+                let mut index =
+                    region.assign_advice_from_constant(|| "index", index_column, 0, F::ZERO)?;
+                for (i, bit) in bits.iter().rev().enumerate() {
+                    let row = i + 1;
+                    config.q_index.enable(&mut region, row)?;
+                    bit.copy_advice(|| "bit", &mut region, bit_column, row)?;
+                    let value = index
+                        .value()
+                        .zip(bit.value())
+                        .map(|(&index, &bit)| index + index + bit);
+                    index = region.assign_advice(|| "index", index_column, row, || value)?;
+                }
+                Ok(index)
+            },
+        )
+    }
+}
+
+impl<F, S, const WIDTH: usize, const RATE: usize, const DEPTH: usize> Circuit<F>
+    for NullifierCircuit<F, S, WIDTH, RATE, DEPTH>
+where
+    F: PrimeField,
+    S: Spec<F, WIDTH, RATE>,
+{
+    type Config = PoseidonNullifierConfig<F, S, WIDTH, RATE>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self {
+            secret: Value::unknown(),
+            nk: Value::unknown(),
+            path_elements: vec![Value::unknown(); DEPTH],
+            path_indices: vec![Value::unknown(); DEPTH],
+            _spec: PhantomData,
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> PoseidonNullifierConfig<F, S, WIDTH, RATE> {
+        let merkle = MerkleCircuit::<F, PoseidonHash<S, WIDTH, RATE>, DEPTH>::configure(meta);
+        let [index_column, bit_column, _] = merkle.merkle;
+        let q_index = meta.selector();
+
+        // the bits are boolean already, the Merkle circuit checks them
+        meta.create_gate("leaf index", |meta| {
+            let q = meta.query_selector(q_index);
+            let prev = meta.query_advice(index_column, Rotation::prev());
+            let bit = meta.query_advice(bit_column, Rotation::cur());
+            let index = meta.query_advice(index_column, Rotation::cur());
+            vec![q * (prev * Expression::Constant(F::from(2)) + bit - index)]
+        });
+
+        NullifierConfig { merkle, q_index }
+    }
+
+    fn synthesize(
+        &self,
+        config: PoseidonNullifierConfig<F, S, WIDTH, RATE>,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), ErrorFront> {
+        let [column, _, _] = config.merkle.merkle;
+        let (secret, nk) = layouter.assign_region(
+            || "load note",
+            |mut region| {
+                let secret = region.assign_advice(|| "secret", column, 0, || self.secret)?;
+                let nk = region.assign_advice(|| "nk", column, 1, || self.nk)?;
+                Ok((secret, nk))
+            },
+        )?;
+
+        let commitment = PoseidonHash::<S, WIDTH, RATE>::hash::<F, 2, 3>(
+            &config.merkle.hash,
+            layouter.namespace(|| "commitment"),
+            COMMITMENT_TAG,
+            [&secret, &nk],
+        )?;
+
+        let path = MerkleCircuit::<F, PoseidonHash<S, WIDTH, RATE>, DEPTH> {
+            leaf: Value::unknown(),
+            path_elements: self.path_elements.clone(),
+            path_indices: self.path_indices.clone(),
+            _hash: PhantomData,
+        };
+        let (root, bits) =
+            path.root_of(&config.merkle, layouter.namespace(|| "root"), &commitment)?;
+        layouter.constrain_instance(root.cell(), config.merkle.root_hash, 0)?;
+
+        let index = Self::leaf_index(&config, layouter.namespace(|| "leaf index"), &bits)?;
+        let nullifier = PoseidonHash::<S, WIDTH, RATE>::hash::<F, 2, 3>(
+            &config.merkle.hash,
+            layouter.namespace(|| "nullifier"),
+            NULLIFIER_TAG,
+            [&nk, &index],
+        )?;
+        layouter.constrain_instance(nullifier.cell(), config.merkle.root_hash, 1)
+    }
+}
+
+/// The leaf of a note
+pub fn native_commitment<F, S, const WIDTH: usize, const RATE: usize>(secret: F, nk: F) -> F
+where
+    F: PrimeField,
+    S: Spec<F, WIDTH, RATE>,
+{
+    poseidon::Hash::<_, S, ConstantLength<3>, WIDTH, RATE>::init().hash([
+        F::from(COMMITMENT_TAG),
+        secret,
+        nk,
+    ])
+}
+
+/// What spending the note at `index` reveals
+pub fn native_nullifier<F, S, const WIDTH: usize, const RATE: usize>(nk: F, index: u64) -> F
+where
+    F: PrimeField,
+    S: Spec<F, WIDTH, RATE>,
+{
+    poseidon::Hash::<_, S, ConstantLength<3>, WIDTH, RATE>::init().hash([
+        F::from(NULLIFIER_TAG),
+        nk,
+        F::from(index),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_poseidon::poseidon::primitives::P128Pow5T3 as OrchardNullifier;
+    use halo2_proofs::{dev::MockProver, halo2curves::pasta::Fp};
+
+    use super::*;
+    use crate::{
+        layout::Layout,
+        merkle::{MerkleTree, Padding},
+    };
+
+    type Spend = NullifierCircuit<Fp, OrchardNullifier, 3, 2, 2>;
+    type Tree = MerkleTree<Fp, PoseidonHash<OrchardNullifier, 3, 2>>;
+
+    fn spend(tree: &Tree, (secret, nk): (Fp, Fp), index: usize) -> Spend {
+        let (path_elements, path_indices) = tree.path(index).unwrap();
+        Spend {
+            secret: Value::known(secret),
+            nk: Value::known(nk),
+            path_elements: path_elements.into_iter().map(Value::known).collect(),
+            path_indices: path_indices.into_iter().map(Value::known).collect(),
+            _spec: PhantomData,
+        }
+    }
+
+    #[test]
+    fn double_spend_reveals_the_same_nullifier() {
+        let notes = (0..4u64)
+            .map(|i| (Fp::from(100 + i), Fp::from(200 + i)))
+            .collect::<Vec<_>>();
+        let commitments = notes
+            .iter()
+            .map(|&(secret, nk)| native_commitment::<_, OrchardNullifier, 3, 2>(secret, nk))
+            .collect::<Vec<_>>();
+
+        // note 2 spent once, and again after note 3 joined the tree
+        let before = Tree::new(&commitments[..3], 2, Padding::Zero).unwrap();
+        let after = Tree::new(&commitments, 2, Padding::Zero).unwrap();
+        assert_ne!(before.root(), after.root());
+
+        let nullifier = native_nullifier::<_, OrchardNullifier, 3, 2>(notes[2].1, 2);
+        for tree in [&before, &after] {
+            let circuit = spend(tree, notes[2], 2);
+            let k = Layout::<Fp>::row_count(&circuit).unwrap().k;
+            MockProver::run(k, &circuit, vec![vec![tree.root(), nullifier]])
+                .unwrap()
+                .assert_satisfied();
+        }
+    }
+}
@@ -0,0 +1,150 @@
+use halo2_proofs::{circuit::Value, halo2curves::ff::PrimeField};
+use std::{fmt, marker::PhantomData};
+
+use super::{MerkleCircuit, MerkleHashGadget};
+
+/// What goes in the leaves past the last one, up to `2^depth` leaves.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Padding<F: PrimeField> {
+    #[default]
+    Zero,
+    /// Always the same leaf.
+    Leaf(F),
+    /// Copies of the last leaf.
+    RepeatLast,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TreeError {
+    /// More leaves than `2^depth`.
+    TooManyLeaves { leaves: usize, depth: usize },
+    /// `RepeatLast` has no leaf to repeat.
+    Empty,
+    /// Not a leaf of the tree, padding included.
+    IndexOutOfRange(usize),
+    /// The circuit asked for is not as deep as the tree.
+    DepthMismatch { tree: usize, circuit: usize },
+}
+
+impl fmt::Display for TreeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TreeError::TooManyLeaves { leaves, depth } => {
+                write!(
+                    f,
+                    "{} leaves don't fit in a tree of depth {}",
+                    leaves, depth
+                )
+            }
+            TreeError::Empty => write!(f, "no leaf to repeat"),
+            TreeError::IndexOutOfRange(index) => write!(f, "no leaf at index {}", index),
+            TreeError::DepthMismatch { tree, circuit } => write!(
+                f,
+                "the tree has a depth of {}, the circuit of {}",
+                tree, circuit
+            ),
+        }
+    }
+}
+
+impl std::error::Error for TreeError {}
+
+/// A Merkle tree hashed with `H` outside of the circuit, to get the root and the
+/// witness of a `MerkleCircuit` for any leaf.
+#[derive(Debug, Clone)]
+pub struct MerkleTree<F: PrimeField, H: MerkleHashGadget<F>> {
+    /// the leaves, padding included
+    leaves: Vec<F>,
+    /// every level of hashes, from the hashed leaves to the root
+    levels: Vec<Vec<F>>,
+    _hash: PhantomData<H>,
+}
+
+impl<F: PrimeField, H: MerkleHashGadget<F>> MerkleTree<F, H> {
+    /// Builds a tree of `2^depth` leaves, the ones after `leaves` come from `padding`.
+    pub fn new(leaves: &[F], depth: usize, padding: Padding<F>) -> Result<Self, TreeError> {
+        let size = 1usize << depth;
+        if leaves.len() > size {
+            return Err(TreeError::TooManyLeaves {
+                leaves: leaves.len(),
+                depth,
+            });
+        }
+        let pad = match padding {
+            Padding::Zero => F::ZERO,
+            Padding::Leaf(leaf) => leaf,
+            Padding::RepeatLast => *leaves.last().ok_or(TreeError::Empty)?,
+        };
+        let mut leaves = leaves.to_vec();
+        leaves.resize(size, pad);
+
+        let mut levels: Vec<Vec<F>> =
+            vec![leaves.iter().map(|&leaf| H::native_leaf(leaf)).collect()];
+        for _ in 0..depth {
+            let level = levels
+                .last()
+                .unwrap()
+                .chunks(2)
+                .map(|pair| H::native_node(pair[0], pair[1]))
+                .collect();
+            levels.push(level);
+        }
+
+        Ok(Self {
+            leaves,
+            levels,
+            _hash: PhantomData,
+        })
+    }
+
+    pub fn depth(&self) -> usize {
+        self.levels.len() - 1
+    }
+
+    pub fn root(&self) -> F {
+        self.levels[self.depth()][0]
+    }
+
+    pub fn leaf(&self, index: usize) -> Option<F> {
+        self.leaves.get(index).copied()
+    }
+
+    /// The hash of the node at `index` in `level`, 0 being the hashed leaves.
+    pub fn node(&self, level: usize, index: usize) -> Option<F> {
+        self.levels.get(level)?.get(index).copied()
+    }
+
+    /// The siblings and the swap bits from the leaf at `index` to the root.
+    pub fn path(&self, index: usize) -> Result<(Vec<F>, Vec<F>), TreeError> {
+        if index >= self.leaves.len() {
+            return Err(TreeError::IndexOutOfRange(index));
+        }
+        Ok((0..self.depth())
+            .map(|level| {
+                let node = index >> level;
+                let sibling = self.levels[level][node ^ 1];
+                (sibling, F::from((node & 1) as u64))
+            })
+            .unzip())
+    }
+
+    /// The witness proving the leaf at `index` is in the tree.
+    pub fn circuit<const DEPTH: usize>(
+        &self,
+        index: usize,
+    ) -> Result<MerkleCircuit<F, H, DEPTH>, TreeError> {
+        if DEPTH != self.depth() {
+            return Err(TreeError::DepthMismatch {
+                tree: self.depth(),
+                circuit: DEPTH,
+            });
+        }
+        let (path_elements, path_indices) = self.path(index)?;
+        Ok(MerkleCircuit {
+            leaf: Value::known(self.leaves[index]),
+            path_elements: path_elements.into_iter().map(Value::known).collect(),
+            path_indices: path_indices.into_iter().map(Value::known).collect(),
+            _hash: PhantomData,
+        })
+    }
+}
@@ -1,14 +1,19 @@
 use halo2_proofs::{
     circuit::Value,
-    dev::{CellValue, MockProver},
-    halo2curves::bn256::Fr as Fp,
+    halo2curves::{bn256::Fr as Fp, pasta::Fp as PallasFp},
 };
 
-mod mul0;
-mod mul1;
-mod mul2;
+use crate::{
+    scenario::{CircuitScenario, Registry, Verdict},
+    tamper::{CellTarget, Tamper},
+};
+
+pub mod mul0;
+pub mod mul1;
+pub mod mul2;
+pub mod mul3;
 
-pub fn multiplication() {
+pub fn register(registry: &mut Registry) {
     let result = Fp::from(12);
 
     // ======================================================
@@ -17,22 +22,30 @@ pub fn multiplication() {
         a: Value::known(Fp::from(2)),
         b: Value::known(Fp::from(3)),
     };
-    let prover = MockProver::run(4, &circuit0, vec![vec![result]]).unwrap();
-
     // 2 * 3 == 12 ???
     // result is wrong, but the proof is still valid
-    assert!(prover.verify().is_ok());
+    registry.register(CircuitScenario::new(
+        "mul0/wrong-result",
+        circuit0,
+        4,
+        vec![vec![result]],
+        Verdict::Accept,
+    ));
 
     // ======================================================
     // we need to constrain the result, that's what we do in mul1.rs
-
     let circuit1 = mul1::MultiplicationCircuit {
         a: Value::known(Fp::from(2)),
         b: Value::known(Fp::from(3)),
     };
-    let prover = MockProver::run(4, &circuit1, vec![vec![result]]).unwrap();
     // we get an error, as we should!
-    assert!(prover.verify().is_err());
+    registry.register(CircuitScenario::new(
+        "mul1/wrong-result",
+        circuit1,
+        4,
+        vec![vec![result]],
+        Verdict::Reject,
+    ));
 
     // ======================================================
     // now let's modify the witness in order to forge our proof
@@ -40,12 +53,18 @@ pub fn multiplication() {
         a: Value::known(Fp::from(2)),
         b: Value::known(Fp::from(3)),
     };
-    let mut prover = MockProver::run(4, &circuit1_bis, vec![vec![result]]).unwrap();
     // we modify directly in the advice the `out` cell (the result of `a * b`)
-    let advice = prover.advice_mut(0);
-    advice[2] = CellValue::Assigned(result);
     // now the proof is valid! 😱
-    assert!(prover.verify().is_ok());
+    registry.register(
+        CircuitScenario::new(
+            "mul1/tampered-out",
+            circuit1_bis,
+            4,
+            vec![vec![result]],
+            Verdict::Accept,
+        )
+        .with_tamper(Tamper::new().set(CellTarget::new("main region", "out"), result)),
+    );
 
     // ======================================================
     // We added a gate in mul2, this should prevent a cheater from modifying the witness
@@ -53,17 +72,70 @@ pub fn multiplication() {
         a: Value::known(Fp::from(2)),
         b: Value::known(Fp::from(3)),
     };
-    let mut prover = MockProver::run(4, &circuit2, vec![vec![result]]).unwrap();
-    // we modify directly in the advice the `out` cell (the result of `a * b`)
-    let advice = prover.advice_mut(0);
-    advice[2] = CellValue::Assigned(result);
     // even if we try to modify the witness, the proof is not valid
-    assert!(prover.verify().is_err());
+    registry.register(
+        CircuitScenario::new(
+            "mul2/tampered-out",
+            circuit2,
+            4,
+            vec![vec![result]],
+            Verdict::Reject,
+        )
+        .with_tamper(Tamper::new().set(CellTarget::new("main region", "out"), result)),
+    );
 
     let circuit2_bis = mul2::MultiplicationCircuit {
         a: Value::known(Fp::from(3)),
         b: Value::known(Fp::from(4)),
     };
-    let prover = MockProver::run(4, &circuit2_bis, vec![vec![result]]).unwrap();
-    assert!(prover.verify().is_ok());
+    registry.register(CircuitScenario::new(
+        "mul2/honest",
+        circuit2_bis,
+        4,
+        vec![vec![result]],
+        Verdict::Accept,
+    ));
+
+    // ======================================================
+    // mul3 reuses the mul2 gate through `MultiplicationChip`, chaining products
+    // 2 * 3 * 2 == 12, on bn256...
+    let factors = [2, 3, 2];
+    let circuit3 = mul3::ProductCircuit {
+        factors: factors.iter().map(|f| Value::known(Fp::from(*f))).collect(),
+    };
+    registry.register(CircuitScenario::new(
+        "mul3/honest",
+        circuit3.clone(),
+        5,
+        vec![vec![result]],
+        Verdict::Accept,
+    ));
+
+    // ...and on pasta, same chip
+    let circuit3_pasta = mul3::ProductCircuit {
+        factors: factors
+            .iter()
+            .map(|f| Value::known(PallasFp::from(*f)))
+            .collect(),
+    };
+    registry.register(CircuitScenario::new(
+        "mul3/honest-pasta",
+        circuit3_pasta,
+        5,
+        vec![vec![PallasFp::from(12)]],
+        Verdict::Accept,
+    ));
+
+    // the intermediate product is copied into the next region,
+    // changing it breaks either the gate or the copy constraint
+    registry.register(
+        CircuitScenario::new(
+            "mul3/tampered-intermediate",
+            circuit3,
+            5,
+            vec![vec![result]],
+            Verdict::Reject,
+        )
+        .with_tamper(Tamper::new().set(CellTarget::new("mul", "out"), Fp::from(4))),
+    );
 }
@@ -3,10 +3,13 @@ use halo2_proofs::{
     halo2curves::bn256::Fr as Fp,
     plonk::{Advice, Circuit, Column, ConstraintSystem, ErrorFront, Instance},
 };
+use serde::Deserialize;
 
-#[derive(Debug, Default, Clone, Copy)]
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
 pub struct MultiplicationCircuit {
+    #[serde(default, deserialize_with = "crate::inputs::value")]
     pub a: Value<Fp>,
+    #[serde(default, deserialize_with = "crate::inputs::value")]
     pub b: Value<Fp>,
 }
 
@@ -4,10 +4,13 @@ use halo2_proofs::{
     plonk::{Advice, Circuit, Column, ConstraintSystem, ErrorFront, Instance, Selector},
     poly::Rotation,
 };
+use serde::Deserialize;
 
-#[derive(Debug, Default, Clone, Copy)]
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
 pub struct MultiplicationCircuit {
+    #[serde(default, deserialize_with = "crate::inputs::value")]
     pub a: Value<Fp>,
+    #[serde(default, deserialize_with = "crate::inputs::value")]
     pub b: Value<Fp>,
 }
 
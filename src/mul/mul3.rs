@@ -0,0 +1,62 @@
+use halo2_proofs::{
+    circuit::{Layouter, SimpleFloorPlanner, Value},
+    halo2curves::ff::PrimeField,
+    plonk::{Circuit, Column, ConstraintSystem, ErrorFront, Instance},
+};
+use serde::Deserialize;
+
+use crate::chips::{MultiplicationChip, MultiplicationConfig};
+
+/// `mul2` with any number of factors, over any field.
+#[derive(Debug, Default, Clone, Deserialize)]
+pub struct ProductCircuit<F: PrimeField> {
+    #[serde(default, deserialize_with = "crate::inputs::values")]
+    pub factors: Vec<Value<F>>,
+}
+
+#[derive(Clone, Debug)]
+pub struct ProductConfig {
+    mul: MultiplicationConfig,
+    instance: Column<Instance>,
+}
+
+impl<F: PrimeField> Circuit<F> for ProductCircuit<F> {
+    type Config = ProductConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self {
+            factors: vec![Value::unknown(); self.factors.len()],
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        let advice = meta.advice_column();
+        let instance = meta.instance_column();
+        meta.enable_equality(instance);
+
+        ProductConfig {
+            mul: MultiplicationChip::configure(meta, advice),
+            instance,
+        }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), ErrorFront> {
+        let chip = MultiplicationChip::construct(config.mul);
+
+        let factors = self
+            .factors
+            .iter()
+            .map(|f| chip.load_private(layouter.namespace(|| "factor"), *f))
+            .collect::<Result<Vec<_>, _>>()?;
+        let out = chip.product(layouter.namespace(|| "product"), &factors)?;
+
+        layouter.constrain_instance(out.cell(), config.instance, 0)?;
+
+        Ok(())
+    }
+}
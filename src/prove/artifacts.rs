@@ -0,0 +1,180 @@
+//! A proof on disk, one directory per proof, so other verifiers can check our forgeries:
+//!
+//! - `meta`: one `key value` per line, `scheme` (`kzg` or `ipa`), `curve` (`bn256` or `pasta`) and `k`
+//! - `pk`, `vk`: the keys, written by halo2 with `SerdeFormat::RawBytes`
+//! - `instances`: one line per instance column, its values as `0x` prefixed big endian hex,
+//!   separated by commas. An empty line is an empty column.
+//! - `proof`: the transcript bytes, Blake2b with `Challenge255`
+//!
+//! The verifying key only stores the commitments: reading it back needs the circuit,
+//! to rebuild the constraint system.
+
+use halo2_proofs::{
+    halo2curves::ff::PrimeField,
+    plonk::{vk_read, Circuit, ProvingKey},
+    SerdeFormat,
+};
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Write},
+    path::Path,
+};
+
+use super::{Backend, Proof};
+
+const FORMAT: SerdeFormat = SerdeFormat::RawBytes;
+
+fn invalid(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn write_file(
+    path: &Path,
+    write: impl FnOnce(&mut BufWriter<File>) -> io::Result<()>,
+) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    write(&mut writer)?;
+    writer.flush()
+}
+
+fn to_hex<F: Backend>(v: &F) -> String {
+    let repr = v.to_repr();
+    let digits: String = repr
+        .as_ref()
+        .iter()
+        .rev()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    format!("0x{}", digits)
+}
+
+fn from_hex<F: Backend>(s: &str) -> io::Result<F> {
+    let digits = s
+        .trim()
+        .strip_prefix("0x")
+        .ok_or_else(|| invalid(format!("`{}` doesn't start with 0x", s)))?;
+    let mut repr = F::Repr::default();
+    if !digits.is_ascii() || digits.len() != 2 * repr.as_ref().len() {
+        return Err(invalid(format!(
+            "`{}` is not {} bytes long",
+            s,
+            repr.as_ref().len()
+        )));
+    }
+    // big endian in the file, little endian in the repr
+    for (i, byte) in repr.as_mut().iter_mut().rev().enumerate() {
+        *byte = u8::from_str_radix(&digits[2 * i..2 * i + 2], 16)
+            .map_err(|_| invalid(format!("`{}` is not hex", s)))?;
+    }
+    Option::from(F::from_repr(repr)).ok_or_else(|| invalid(format!("`{}` is not in the field", s)))
+}
+
+impl<F: Backend> Proof<F> {
+    pub fn save(&self, dir: &Path, pk: &ProvingKey<F::Curve>) -> io::Result<()> {
+        fs::create_dir_all(dir)?;
+
+        write_file(&dir.join("meta"), |w| {
+            writeln!(w, "scheme {}", F::SCHEME)?;
+            writeln!(w, "curve {}", F::CURVE)?;
+            writeln!(w, "k {}", self.k)
+        })?;
+        write_file(&dir.join("pk"), |w| pk.write(w, FORMAT))?;
+        write_file(&dir.join("vk"), |w| self.vk.write(w, FORMAT))?;
+        write_file(&dir.join("instances"), |w| {
+            for column in self.instances.iter() {
+                let values: Vec<String> = column.iter().map(to_hex).collect();
+                writeln!(w, "{}", values.join(","))?;
+            }
+            Ok(())
+        })?;
+        fs::write(dir.join("proof"), &self.bytes)
+    }
+
+    /// Reads back what `save` wrote, `circuit` only provides the constraint system.
+    pub fn load<C: Circuit<F>>(dir: &Path, circuit: &C) -> io::Result<Self> {
+        let meta = fs::read_to_string(dir.join("meta"))?;
+        let field = |key: &str| {
+            meta.lines()
+                .find_map(|line| line.strip_prefix(key)?.strip_prefix(' '))
+                .ok_or_else(|| invalid(format!("`{}` missing from meta", key)))
+        };
+        if field("scheme")? != F::SCHEME || field("curve")? != F::CURVE {
+            return Err(invalid(format!(
+                "proof made with {} over {}, expected {} over {}",
+                field("scheme")?,
+                field("curve")?,
+                F::SCHEME,
+                F::CURVE
+            )));
+        }
+        let k: u32 = field("k")?
+            .parse()
+            .map_err(|_| invalid("`k` is not a number".to_string()))?;
+
+        // keygen_vk compresses the selectors, the reader has to do the same
+        let vk = vk_read(
+            &mut BufReader::new(File::open(dir.join("vk"))?),
+            FORMAT,
+            k,
+            circuit,
+            true,
+        )?;
+
+        let instances = fs::read_to_string(dir.join("instances"))?
+            .lines()
+            .map(|line| {
+                line.split(',')
+                    .filter(|v| !v.trim().is_empty())
+                    .map(from_hex)
+                    .collect::<io::Result<Vec<F>>>()
+            })
+            .collect::<io::Result<_>>()?;
+
+        Ok(Self {
+            k,
+            vk,
+            instances,
+            bytes: fs::read(dir.join("proof"))?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::{circuit::Value, halo2curves::bn256::Fr};
+    use std::env;
+
+    use super::*;
+    use crate::{
+        mul::mul3::ProductCircuit,
+        prove::{prove, ParamsCache},
+        scenario::Verdict,
+        tamper::Tamper,
+    };
+
+    /// Params, keys and proof written by one run and verified by the next, from the files only.
+    #[test]
+    fn saved_proof_verifies_with_saved_params() {
+        let dir = env::temp_dir().join(format!("halo2-soundness-bugs-{}", std::process::id()));
+        let k = 5;
+        let circuit = ProductCircuit {
+            factors: [2, 3, 2].map(|f| Value::known(Fr::from(f))).to_vec(),
+        };
+        let instances = vec![vec![Fr::from(12)]];
+
+        let cache = ParamsCache::new(dir.join("params"));
+        let params = cache.get::<Fr>(k).unwrap();
+        let (pk, proof) = prove(&params, k, &circuit, instances.clone(), &Tamper::new()).unwrap();
+        proof.save(&dir.join("proof"), &pk).unwrap();
+        assert!(cache.path::<Fr>(k).exists());
+
+        let params = ParamsCache::new(dir.join("params")).get::<Fr>(k).unwrap();
+        let loaded = Proof::<Fr>::load(&dir.join("proof"), &circuit).unwrap();
+        assert_eq!(loaded.k, k);
+        assert_eq!(loaded.instances, instances);
+        assert_eq!(loaded.bytes, proof.bytes);
+        assert_eq!(loaded.verify(&params), Verdict::Accept);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,337 @@
+use halo2_proofs::{
+    arithmetic::CurveAffine,
+    halo2curves::{
+        bn256::{Bn256, Fr, G1Affine},
+        ff::FromUniformBytes,
+        pasta::{EqAffine, Fp},
+    },
+    helpers::{SerdeCurveAffine, SerdePrimeField},
+    plonk::{
+        create_proof, keygen_pk, keygen_vk, verify_proof, Circuit, Error, ProvingKey, VerifyingKey,
+    },
+    poly::{
+        commitment::{Params as _, ParamsProver},
+        ipa::{
+            commitment::{IPACommitmentScheme, ParamsIPA},
+            multiopen::{ProverIPA, VerifierIPA},
+            strategy::SingleStrategy as IpaStrategy,
+        },
+        kzg::{
+            commitment::{KZGCommitmentScheme, ParamsKZG},
+            multiopen::{ProverSHPLONK, VerifierSHPLONK},
+            strategy::SingleStrategy as KzgStrategy,
+        },
+    },
+    transcript::{
+        Blake2bRead, Blake2bWrite, Challenge255, TranscriptReadBuffer, TranscriptWriterBuffer,
+    },
+};
+use rand::{rngs::OsRng, RngCore};
+use std::{
+    fmt,
+    io::{self, Read, Write},
+};
+
+use crate::{
+    layout::Layout,
+    scenario::Verdict,
+    tamper::{Tamper, TamperError},
+};
+
+mod artifacts;
+mod params;
+mod tampered;
+pub use params::ParamsCache;
+pub use tampered::{with_overrides, TamperPlanner, TamperedCircuit};
+
+/// The commitment scheme we prove with, picked by the field of the circuit:
+/// KZG with SHPLONK over bn256, IPA over pasta.
+pub trait Backend: FromUniformBytes<64> + SerdePrimeField + Ord {
+    const SCHEME: &'static str;
+    const CURVE: &'static str;
+    type Curve: CurveAffine<ScalarExt = Self> + SerdeCurveAffine;
+    type Params;
+
+    /// The IPA setup is transparent and ignores `rng`.
+    fn setup(k: u32, rng: impl RngCore) -> Self::Params;
+    fn read_params(reader: &mut impl Read) -> io::Result<Self::Params>;
+    fn write_params(params: &Self::Params, writer: &mut impl Write) -> io::Result<()>;
+
+    fn keygen<C: Circuit<Self>>(
+        params: &Self::Params,
+        circuit: &C,
+    ) -> Result<ProvingKey<Self::Curve>, Error>;
+
+    fn prove<C: Circuit<Self>>(
+        params: &Self::Params,
+        pk: &ProvingKey<Self::Curve>,
+        circuit: C,
+        instances: Vec<Vec<Self>>,
+    ) -> Result<Vec<u8>, Error>;
+
+    fn verify(
+        params: &Self::Params,
+        vk: &VerifyingKey<Self::Curve>,
+        instances: Vec<Vec<Self>>,
+        proof: &[u8],
+    ) -> bool;
+}
+
+impl Backend for Fr {
+    const SCHEME: &'static str = "kzg";
+    const CURVE: &'static str = "bn256";
+    type Curve = G1Affine;
+    type Params = ParamsKZG<Bn256>;
+
+    fn setup(k: u32, rng: impl RngCore) -> Self::Params {
+        ParamsKZG::<Bn256>::setup(k, rng)
+    }
+
+    fn read_params(reader: &mut impl Read) -> io::Result<Self::Params> {
+        ParamsKZG::read(reader)
+    }
+
+    fn write_params(params: &Self::Params, writer: &mut impl Write) -> io::Result<()> {
+        params.write(writer)
+    }
+
+    fn keygen<C: Circuit<Self>>(
+        params: &Self::Params,
+        circuit: &C,
+    ) -> Result<ProvingKey<G1Affine>, Error> {
+        let vk = keygen_vk(params, circuit)?;
+        keygen_pk(params, vk, circuit)
+    }
+
+    fn prove<C: Circuit<Self>>(
+        params: &Self::Params,
+        pk: &ProvingKey<G1Affine>,
+        circuit: C,
+        instances: Vec<Vec<Self>>,
+    ) -> Result<Vec<u8>, Error> {
+        let mut transcript = Blake2bWrite::<_, G1Affine, Challenge255<_>>::init(vec![]);
+        create_proof::<
+            KZGCommitmentScheme<Bn256>,
+            ProverSHPLONK<'_, Bn256>,
+            Challenge255<G1Affine>,
+            _,
+            Blake2bWrite<Vec<u8>, G1Affine, Challenge255<G1Affine>>,
+            _,
+        >(params, pk, &[circuit], &[instances], OsRng, &mut transcript)?;
+        Ok(transcript.finalize())
+    }
+
+    fn verify(
+        params: &Self::Params,
+        vk: &VerifyingKey<G1Affine>,
+        instances: Vec<Vec<Self>>,
+        proof: &[u8],
+    ) -> bool {
+        let mut transcript = Blake2bRead::<_, G1Affine, Challenge255<_>>::init(proof);
+        verify_proof::<
+            KZGCommitmentScheme<Bn256>,
+            VerifierSHPLONK<'_, Bn256>,
+            Challenge255<G1Affine>,
+            Blake2bRead<&[u8], G1Affine, Challenge255<G1Affine>>,
+            KzgStrategy<'_, Bn256>,
+        >(
+            params.verifier_params(),
+            vk,
+            KzgStrategy::new(params),
+            &[instances],
+            &mut transcript,
+        )
+        .is_ok()
+    }
+}
+
+impl Backend for Fp {
+    const SCHEME: &'static str = "ipa";
+    const CURVE: &'static str = "pasta";
+    type Curve = EqAffine;
+    type Params = ParamsIPA<EqAffine>;
+
+    fn setup(k: u32, _rng: impl RngCore) -> Self::Params {
+        ParamsIPA::<EqAffine>::new(k)
+    }
+
+    fn read_params(reader: &mut impl Read) -> io::Result<Self::Params> {
+        ParamsIPA::read(reader)
+    }
+
+    fn write_params(params: &Self::Params, writer: &mut impl Write) -> io::Result<()> {
+        params.write(writer)
+    }
+
+    fn keygen<C: Circuit<Self>>(
+        params: &Self::Params,
+        circuit: &C,
+    ) -> Result<ProvingKey<EqAffine>, Error> {
+        let vk = keygen_vk(params, circuit)?;
+        keygen_pk(params, vk, circuit)
+    }
+
+    fn prove<C: Circuit<Self>>(
+        params: &Self::Params,
+        pk: &ProvingKey<EqAffine>,
+        circuit: C,
+        instances: Vec<Vec<Self>>,
+    ) -> Result<Vec<u8>, Error> {
+        let mut transcript = Blake2bWrite::<_, EqAffine, Challenge255<_>>::init(vec![]);
+        create_proof::<
+            IPACommitmentScheme<EqAffine>,
+            ProverIPA<'_, EqAffine>,
+            Challenge255<EqAffine>,
+            _,
+            Blake2bWrite<Vec<u8>, EqAffine, Challenge255<EqAffine>>,
+            _,
+        >(params, pk, &[circuit], &[instances], OsRng, &mut transcript)?;
+        Ok(transcript.finalize())
+    }
+
+    fn verify(
+        params: &Self::Params,
+        vk: &VerifyingKey<EqAffine>,
+        instances: Vec<Vec<Self>>,
+        proof: &[u8],
+    ) -> bool {
+        let mut transcript = Blake2bRead::<_, EqAffine, Challenge255<_>>::init(proof);
+        verify_proof::<
+            IPACommitmentScheme<EqAffine>,
+            VerifierIPA<'_, EqAffine>,
+            Challenge255<EqAffine>,
+            Blake2bRead<&[u8], EqAffine, Challenge255<EqAffine>>,
+            IpaStrategy<'_, EqAffine>,
+        >(
+            params.verifier_params(),
+            vk,
+            IpaStrategy::new(params),
+            &[instances],
+            &mut transcript,
+        )
+        .is_ok()
+    }
+}
+
+#[derive(Debug)]
+pub enum ProveError {
+    Tamper(TamperError),
+    Params(io::Error),
+    /// Key generation failed, the circuit itself is broken (e.g. `k` is too small).
+    Keygen(Error),
+    /// The prover refuses some witnesses by itself, e.g. a lookup input missing
+    /// from its table can't be permuted.
+    Refused(Error),
+    Artifacts(io::Error),
+}
+
+impl fmt::Display for ProveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProveError::Tamper(err) => write!(f, "{}", err),
+            ProveError::Params(err) => write!(f, "can't load the params: {}", err),
+            ProveError::Keygen(err) => write!(f, "keygen failed: {}", err),
+            ProveError::Refused(err) => write!(f, "the prover refused the witness: {}", err),
+            ProveError::Artifacts(err) => write!(f, "can't access the artifacts: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for ProveError {}
+
+impl From<TamperError> for ProveError {
+    fn from(err: TamperError) -> Self {
+        ProveError::Tamper(err)
+    }
+}
+
+/// A proof from the real prover, with what a verifier needs to check it.
+#[derive(Debug, Clone)]
+pub struct Proof<F: Backend> {
+    pub k: u32,
+    pub vk: VerifyingKey<F::Curve>,
+    pub instances: Vec<Vec<F>>,
+    pub bytes: Vec<u8>,
+}
+
+impl<F: Backend> Proof<F> {
+    pub fn verify(&self, params: &F::Params) -> Verdict {
+        if F::verify(params, &self.vk, self.instances.clone(), &self.bytes) {
+            Verdict::Accept
+        } else {
+            Verdict::Reject
+        }
+    }
+}
+
+/// Runs keygen and `create_proof` on `circuit` with `tamper` applied to its witness.
+///
+/// The keys come from the untouched circuit: tampering only changes the witness,
+/// exactly like a cheating prover holding the same proving key as everyone else.
+pub fn prove<F: Backend, C: Circuit<F> + Clone>(
+    params: &F::Params,
+    k: u32,
+    circuit: &C,
+    instances: Vec<Vec<F>>,
+    tamper: &Tamper<F>,
+) -> Result<(ProvingKey<F::Curve>, Proof<F>), ProveError> {
+    let overrides = tamper.resolve(&Layout::synthesize(circuit).map_err(TamperError::from)?)?;
+    let circuit = TamperedCircuit(circuit.clone());
+
+    let pk = F::keygen(params, &circuit).map_err(ProveError::Keygen)?;
+    let bytes = with_overrides(overrides, || {
+        F::prove(params, &pk, circuit, instances.clone())
+    })
+    .map_err(ProveError::Refused)?;
+
+    let proof = Proof {
+        k,
+        vk: pk.get_vk().clone(),
+        instances,
+        bytes,
+    };
+    Ok((pk, proof))
+}
+
+/// Proves and verifies in one go, and returns what the real verifier says.
+///
+/// A witness the prover refuses is `ProveError::Refused`, not a `Reject`:
+/// there was no proof for the verifier to reject.
+pub fn prove_and_verify<F: Backend, C: Circuit<F> + Clone>(
+    cache: &ParamsCache,
+    k: u32,
+    circuit: &C,
+    instances: Vec<Vec<F>>,
+    tamper: &Tamper<F>,
+) -> Result<Verdict, ProveError> {
+    let params = cache.get::<F>(k).map_err(ProveError::Params)?;
+    let (_, proof) = prove(&params, k, circuit, instances, tamper)?;
+    Ok(proof.verify(&params))
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::circuit::Value;
+
+    use super::*;
+    use crate::{mul::mul3::ProductCircuit, tamper::CellTarget};
+
+    /// `mul3/tampered-intermediate`: the first product is copied into the second
+    /// multiplication, the prover must still build the proof for the verifier to reject.
+    #[test]
+    fn tampered_cell_copied_downstream_is_rejected() {
+        let k = 5;
+        let params = Fp::setup(k, OsRng);
+        let circuit = ProductCircuit {
+            factors: [2, 3, 2].map(|f| Value::known(Fp::from(f))).to_vec(),
+        };
+        let instances = vec![vec![Fp::from(12)]];
+
+        let (_, honest) = prove(&params, k, &circuit, instances.clone(), &Tamper::new()).unwrap();
+        assert_eq!(honest.verify(&params), Verdict::Accept);
+
+        let tamper = Tamper::new().set(CellTarget::new("mul", "out"), Fp::from(4));
+        let (_, tampered) = prove(&params, k, &circuit, instances, &tamper).unwrap();
+        assert_eq!(tampered.verify(&params), Verdict::Reject);
+    }
+}
@@ -0,0 +1,60 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use rand::{rngs::StdRng, SeedableRng};
+
+use super::Backend;
+
+/// Seed of the KZG setup. Anyone can recompute the toxic waste from it,
+/// which is fine for forging demos and nothing else.
+const SEED: u64 = 0;
+
+/// Params on disk, one file per backend and `k`, generated the first time they're asked for.
+#[derive(Debug, Clone)]
+pub struct ParamsCache {
+    dir: PathBuf,
+}
+
+impl Default for ParamsCache {
+    fn default() -> Self {
+        Self::new("params")
+    }
+}
+
+impl ParamsCache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// e.g. `params/kzg-bn256-k10.params`
+    pub fn path<F: Backend>(&self, k: u32) -> PathBuf {
+        self.dir
+            .join(format!("{}-{}-k{}.params", F::SCHEME, F::CURVE, k))
+    }
+
+    /// Loads the params for `k`, or runs the setup and saves them for next time.
+    pub fn get<F: Backend>(&self, k: u32) -> io::Result<F::Params> {
+        let path = self.path::<F>(k);
+        if path.exists() {
+            return F::read_params(&mut BufReader::new(File::open(&path)?));
+        }
+
+        let params = F::setup(k, StdRng::seed_from_u64(SEED));
+        fs::create_dir_all(&self.dir)?;
+        // write next to the final file and rename, so an interrupted run
+        // doesn't leave truncated params behind
+        let tmp = path.with_extension("tmp");
+        let mut writer = BufWriter::new(File::create(&tmp)?);
+        F::write_params(&params, &mut writer)?;
+        writer.flush()?;
+        fs::rename(&tmp, &path)?;
+        Ok(params)
+    }
+}
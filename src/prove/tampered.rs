@@ -0,0 +1,206 @@
+use halo2_proofs::{
+    arithmetic::Field,
+    circuit::{layouter::SyncDeps, Layouter, Value},
+    plonk::{
+        Advice, Any, Assigned, Assignment, Challenge, Circuit, Column, ConstraintSystem,
+        ErrorFront, Fixed, FloorPlanner, Instance, Selector,
+    },
+};
+use std::{any::Any as StdAny, cell::RefCell, marker::PhantomData};
+
+use crate::tamper::Override;
+
+thread_local! {
+    static OVERRIDES: RefCell<Option<Box<dyn StdAny>>> = const { RefCell::new(None) };
+}
+
+/// Runs `f` with `overrides` applied to every `TamperedCircuit` synthesized meanwhile.
+///
+/// The real prover only knows the witness the circuit assigns, there is no `advice_mut`.
+/// The floor planner is the only piece we control on that path, and its `synthesize`
+/// is generic over the circuit, so the overrides reach it through this thread local.
+pub fn with_overrides<F: Field, T>(overrides: Vec<Override<F>>, f: impl FnOnce() -> T) -> T {
+    OVERRIDES.with(|o| *o.borrow_mut() = Some(Box::new(overrides)));
+    let result = f();
+    OVERRIDES.with(|o| *o.borrow_mut() = None);
+    result
+}
+
+/// Same circuit, same constraints and keys, but synthesized by `TamperPlanner`.
+#[derive(Debug, Clone)]
+pub struct TamperedCircuit<C>(pub C);
+
+impl<F: Field, C: Circuit<F>> Circuit<F> for TamperedCircuit<C> {
+    type Config = C::Config;
+    type FloorPlanner = TamperPlanner<C::FloorPlanner>;
+
+    fn without_witnesses(&self) -> Self {
+        Self(self.0.without_witnesses())
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        C::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        layouter: impl Layouter<F>,
+    ) -> Result<(), ErrorFront> {
+        self.0.synthesize(config, layouter)
+    }
+}
+
+/// Delegates to the floor planner `P` of the circuit, overwriting the advice cells
+/// given to `with_overrides` on the way.
+#[derive(Debug)]
+pub struct TamperPlanner<P>(PhantomData<P>);
+
+impl<P: FloorPlanner> FloorPlanner for TamperPlanner<P> {
+    fn synthesize<F: Field, CS: Assignment<F> + SyncDeps, C: Circuit<F>>(
+        cs: &mut CS,
+        circuit: &C,
+        config: C::Config,
+        constants: Vec<Column<Fixed>>,
+    ) -> Result<(), ErrorFront> {
+        let overrides = OVERRIDES.with(|o| {
+            o.borrow()
+                .as_ref()
+                .and_then(|o| o.downcast_ref::<Vec<Override<F>>>())
+                .cloned()
+                .unwrap_or_default()
+        });
+        let mut cs = Overriding {
+            inner: cs,
+            overrides,
+        };
+        P::synthesize(&mut cs, circuit, config, constants)
+    }
+}
+
+struct Overriding<'a, F, CS> {
+    inner: &'a mut CS,
+    overrides: Vec<Override<F>>,
+}
+
+impl<F: Field, CS: Assignment<F>> Assignment<F> for Overriding<'_, F, CS> {
+    fn enter_region<NR, N>(&mut self, name_fn: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        self.inner.enter_region(name_fn)
+    }
+
+    fn annotate_column<A, AR>(&mut self, annotation: A, column: Column<Any>)
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.inner.annotate_column(annotation, column)
+    }
+
+    fn exit_region(&mut self) {
+        self.inner.exit_region()
+    }
+
+    fn enable_selector<A, AR>(
+        &mut self,
+        annotation: A,
+        selector: &Selector,
+        row: usize,
+    ) -> Result<(), ErrorFront>
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.inner.enable_selector(annotation, selector, row)
+    }
+
+    fn query_instance(&self, column: Column<Instance>, row: usize) -> Result<Value<F>, ErrorFront> {
+        self.inner.query_instance(column, row)
+    }
+
+    fn assign_advice<V, VR, A, AR>(
+        &mut self,
+        annotation: A,
+        column: Column<Advice>,
+        row: usize,
+        to: V,
+    ) -> Result<(), ErrorFront>
+    where
+        V: FnOnce() -> Value<VR>,
+        VR: Into<Assigned<F>>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        match self
+            .overrides
+            .iter()
+            .find(|o| o.column == column.index() && o.row == row)
+        {
+            Some(o) => {
+                // the region keeps what `to` returns in its `AssignedCell`, like `MockProver`
+                // after `advice_mut`: copies downstream see the honest value, only the
+                // column gets the override
+                let _ = to();
+                let value = o.value;
+                self.inner
+                    .assign_advice(annotation, column, row, || Value::known(value))
+            }
+            None => self.inner.assign_advice(annotation, column, row, to),
+        }
+    }
+
+    fn assign_fixed<V, VR, A, AR>(
+        &mut self,
+        annotation: A,
+        column: Column<Fixed>,
+        row: usize,
+        to: V,
+    ) -> Result<(), ErrorFront>
+    where
+        V: FnOnce() -> Value<VR>,
+        VR: Into<Assigned<F>>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.inner.assign_fixed(annotation, column, row, to)
+    }
+
+    fn copy(
+        &mut self,
+        left_column: Column<Any>,
+        left_row: usize,
+        right_column: Column<Any>,
+        right_row: usize,
+    ) -> Result<(), ErrorFront> {
+        self.inner
+            .copy(left_column, left_row, right_column, right_row)
+    }
+
+    fn fill_from_row(
+        &mut self,
+        column: Column<Fixed>,
+        row: usize,
+        to: Value<Assigned<F>>,
+    ) -> Result<(), ErrorFront> {
+        self.inner.fill_from_row(column, row, to)
+    }
+
+    fn get_challenge(&self, challenge: Challenge) -> Value<F> {
+        self.inner.get_challenge(challenge)
+    }
+
+    fn push_namespace<NR, N>(&mut self, name_fn: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        self.inner.push_namespace(name_fn)
+    }
+
+    fn pop_namespace(&mut self, gadget_name: Option<String>) {
+        self.inner.pop_namespace(gadget_name)
+    }
+}
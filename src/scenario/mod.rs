@@ -0,0 +1,330 @@
+use halo2_proofs::{
+    dev::MockProver,
+    plonk::{Circuit, ErrorFront},
+};
+use serde::de::DeserializeOwned;
+use std::{borrow::Cow, path::Path};
+
+use crate::{
+    analysis::{self, AnalysisError, CoverageReport, Finding},
+    inputs,
+    layout::{Layout, RowCount},
+    prove::{self, Backend, ParamsCache, Proof, ProveError},
+    tamper::{Tamper, TamperError},
+};
+
+mod options;
+mod runner;
+pub use options::{parse_field, RunOptions};
+pub use runner::{Report, ScenarioResult};
+
+/// What we expect the verifier, mock or real, to say about a scenario.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    Accept,
+    Reject,
+}
+
+impl Verdict {
+    pub fn of<T, E>(result: &Result<T, E>) -> Self {
+        if result.is_ok() {
+            Verdict::Accept
+        } else {
+            Verdict::Reject
+        }
+    }
+}
+
+/// One of the examples of this repo: a circuit, its public inputs,
+/// and whether the verifier should accept it.
+pub trait Scenario {
+    type F: Backend;
+    /// Deserializable so a JSON file can replace the inputs, see `inputs`.
+    type Circuit: Circuit<Self::F> + Clone + DeserializeOwned;
+
+    fn name(&self) -> &'static str;
+    fn circuit(&self) -> &Self::Circuit;
+    fn k(&self) -> u32;
+    fn public_inputs(&self) -> Vec<Vec<Self::F>>;
+    fn expected(&self) -> Verdict;
+
+    /// Witness edits applied after synthesis, before verification.
+    /// That's how we forge proofs the circuit would never produce by itself.
+    fn tamper(&self) -> Tamper<Self::F> {
+        Tamper::new()
+    }
+
+    fn mock_prover(&self) -> Result<MockProver<Self::F>, TamperError> {
+        let mut prover = MockProver::run(self.k(), self.circuit(), self.public_inputs())?;
+        self.tamper().apply(self.circuit(), &mut prover)?;
+        Ok(prover)
+    }
+}
+
+/// Object safe view of a `Scenario`, so scenarios over different fields
+/// and circuits can live in the same registry.
+pub trait DynScenario {
+    fn name(&self) -> &'static str;
+    fn k(&self) -> u32;
+    fn expected(&self) -> Verdict;
+    fn run(&self) -> ScenarioResult;
+    /// Same as `run`, with keygen, `create_proof` and `verify_proof` instead of `MockProver`.
+    fn prove(&self, cache: &ParamsCache) -> ScenarioResult;
+    /// Proves the scenario and writes the keys, instances and proof into `dir`.
+    fn save_proof(&self, cache: &ParamsCache, dir: &Path) -> Result<(), ProveError>;
+    /// Verifies the proof `save_proof` wrote in `dir`, whichever witness it was made from.
+    fn verify_saved(&self, cache: &ParamsCache, dir: &Path) -> ScenarioResult;
+    /// Searches the untampered witness for cells the verifier doesn't pin down.
+    fn analyze(&self) -> Result<Vec<Finding>, AnalysisError>;
+    /// `run` or `prove` with the parameters changed by `options`.
+    fn run_with(&self, options: &RunOptions) -> ScenarioResult;
+    fn coverage(&self) -> Result<CoverageReport, ErrorFront>;
+    /// Rows used by the circuit, and the smallest `k` that fits them.
+    fn row_count(&self) -> Result<RowCount, ErrorFront>;
+}
+
+impl<S: Scenario> DynScenario for S {
+    fn name(&self) -> &'static str {
+        Scenario::name(self)
+    }
+
+    fn k(&self) -> u32 {
+        Scenario::k(self)
+    }
+
+    fn expected(&self) -> Verdict {
+        Scenario::expected(self)
+    }
+
+    fn run(&self) -> ScenarioResult {
+        let name = Scenario::name(self);
+        let expected = Scenario::expected(self);
+        match self.mock_prover() {
+            Ok(prover) => ScenarioResult::verified(name, expected, prover.verify()),
+            Err(err) => ScenarioResult::errored(name, expected, err),
+        }
+    }
+
+    fn prove(&self, cache: &ParamsCache) -> ScenarioResult {
+        let name = Scenario::name(self);
+        let expected = Scenario::expected(self);
+        match prove::prove_and_verify(
+            cache,
+            Scenario::k(self),
+            self.circuit(),
+            self.public_inputs(),
+            &self.tamper(),
+        ) {
+            Ok(observed) => ScenarioResult::proved(name, expected, observed),
+            Err(err) => ScenarioResult::errored(name, expected, err),
+        }
+    }
+
+    fn save_proof(&self, cache: &ParamsCache, dir: &Path) -> Result<(), ProveError> {
+        let k = Scenario::k(self);
+        let params = cache.get::<Self::F>(k).map_err(ProveError::Params)?;
+        let (pk, proof) = prove::prove(
+            &params,
+            k,
+            self.circuit(),
+            self.public_inputs(),
+            &self.tamper(),
+        )?;
+        proof.save(dir, &pk).map_err(ProveError::Artifacts)
+    }
+
+    fn verify_saved(&self, cache: &ParamsCache, dir: &Path) -> ScenarioResult {
+        let name = Scenario::name(self);
+        let expected = Scenario::expected(self);
+        let verified = Proof::<Self::F>::load(dir, self.circuit())
+            .map_err(ProveError::Artifacts)
+            .and_then(|proof| {
+                let params = cache.get::<Self::F>(proof.k).map_err(ProveError::Params)?;
+                Ok(proof.verify(&params))
+            });
+        match verified {
+            Ok(observed) => ScenarioResult::proved(name, expected, observed),
+            Err(err) => ScenarioResult::errored(name, expected, err),
+        }
+    }
+
+    fn analyze(&self) -> Result<Vec<Finding>, AnalysisError> {
+        analysis::find_underconstrained(Scenario::k(self), self.circuit(), self.public_inputs())
+    }
+
+    fn run_with(&self, options: &RunOptions) -> ScenarioResult {
+        let name = Scenario::name(self);
+        let expected = Scenario::expected(self);
+
+        let k = options.k.unwrap_or_else(|| Scenario::k(self));
+        let (circuit, public_inputs) = match &options.input {
+            Some(path) => match inputs::load(path) {
+                Ok((circuit, public_inputs)) => (Cow::Owned(circuit), public_inputs),
+                Err(err) => return ScenarioResult::errored(name, expected, err),
+            },
+            None => (Cow::Borrowed(self.circuit()), self.public_inputs()),
+        };
+        let circuit = circuit.as_ref();
+        let public_inputs = match &options.public_inputs {
+            Some(columns) => columns
+                .iter()
+                .map(|column| column.iter().map(|v| parse_field(v)).collect())
+                .collect::<Result<Vec<Vec<Self::F>>, String>>(),
+            None => Ok(public_inputs),
+        };
+        let tamper = options.cells.iter().try_fold(
+            self.tamper(),
+            |tamper, (target, value)| -> Result<_, String> {
+                Ok(tamper.set(target.clone(), parse_field(value)?))
+            },
+        );
+        let (public_inputs, tamper) = match (public_inputs, tamper) {
+            (Ok(public_inputs), Ok(tamper)) => (public_inputs, tamper),
+            (Err(err), _) | (_, Err(err)) => return ScenarioResult::errored(name, expected, err),
+        };
+
+        match &options.real {
+            Some(cache) => {
+                match prove::prove_and_verify(cache, k, circuit, public_inputs, &tamper) {
+                    Ok(observed) => ScenarioResult::proved(name, expected, observed),
+                    Err(err) => ScenarioResult::errored(name, expected, err),
+                }
+            }
+            None => {
+                let prover = MockProver::run(k, circuit, public_inputs)
+                    .map_err(TamperError::from)
+                    .and_then(|mut prover| {
+                        tamper.apply(circuit, &mut prover)?;
+                        Ok(prover)
+                    });
+                match prover {
+                    Ok(prover) => ScenarioResult::verified(name, expected, prover.verify()),
+                    Err(err) => ScenarioResult::errored(name, expected, err),
+                }
+            }
+        }
+    }
+
+    fn coverage(&self) -> Result<CoverageReport, ErrorFront> {
+        CoverageReport::build(self.circuit())
+    }
+
+    fn row_count(&self) -> Result<RowCount, ErrorFront> {
+        Layout::<S::F>::row_count(self.circuit())
+    }
+}
+
+/// A `Scenario` built from plain values, that's what all the examples use.
+pub struct CircuitScenario<F, C> {
+    name: &'static str,
+    circuit: C,
+    k: u32,
+    public_inputs: Vec<Vec<F>>,
+    expected: Verdict,
+    tamper: Tamper<F>,
+}
+
+impl<F: Backend, C> CircuitScenario<F, C> {
+    pub fn new(
+        name: &'static str,
+        circuit: C,
+        k: u32,
+        public_inputs: Vec<Vec<F>>,
+        expected: Verdict,
+    ) -> Self {
+        Self {
+            name,
+            circuit,
+            k,
+            public_inputs,
+            expected,
+            tamper: Tamper::new(),
+        }
+    }
+
+    pub fn with_tamper(mut self, tamper: Tamper<F>) -> Self {
+        self.tamper = tamper;
+        self
+    }
+}
+
+impl<F: Backend, C: Circuit<F> + Clone + DeserializeOwned> Scenario for CircuitScenario<F, C> {
+    type F = F;
+    type Circuit = C;
+
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn circuit(&self) -> &C {
+        &self.circuit
+    }
+
+    fn k(&self) -> u32 {
+        self.k
+    }
+
+    fn public_inputs(&self) -> Vec<Vec<F>> {
+        self.public_inputs.clone()
+    }
+
+    fn expected(&self) -> Verdict {
+        self.expected
+    }
+
+    fn tamper(&self) -> Tamper<F> {
+        self.tamper.clone()
+    }
+}
+
+#[derive(Default)]
+pub struct Registry {
+    scenarios: Vec<Box<dyn DynScenario>>,
+}
+
+impl Registry {
+    pub fn register(&mut self, scenario: impl Scenario + 'static) {
+        self.scenarios.push(Box::new(scenario));
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &dyn DynScenario> {
+        self.scenarios.iter().map(|s| s.as_ref())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn DynScenario> {
+        self.iter().find(|s| s.name() == name)
+    }
+
+    /// Scenarios whose name contains `pattern`, e.g. "casino" or "mul1/".
+    pub fn filter<'a>(&'a self, pattern: &'a str) -> impl Iterator<Item = &'a dyn DynScenario> {
+        self.iter().filter(move |s| s.name().contains(pattern))
+    }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        self.iter().map(|s| s.name()).collect()
+    }
+
+    /// Runs every scenario, a surprising verdict doesn't stop the others.
+    pub fn run_all(&self) -> Report {
+        self.iter().map(|s| s.run()).collect()
+    }
+
+    /// Runs every scenario through the real prover and verifier. Much slower than `run_all`.
+    pub fn prove_all(&self, cache: &ParamsCache) -> Report {
+        self.iter().map(|s| s.prove(cache)).collect()
+    }
+}
+
+/// Every example of the repo, in the order of the README.
+pub fn registry() -> Registry {
+    let mut registry = Registry::default();
+
+    crate::base::register(&mut registry);
+    crate::mul::register(&mut registry);
+    crate::casino::register(&mut registry);
+    crate::square_root::register(&mut registry);
+    crate::merkle::register(&mut registry);
+    crate::hash::register(&mut registry);
+
+    registry
+}
@@ -0,0 +1,92 @@
+use halo2_proofs::halo2curves::ff::PrimeField;
+use std::path::PathBuf;
+
+use crate::{prove::ParamsCache, tamper::CellTarget};
+
+/// Changes to a scenario decided at run time, e.g. from the command line.
+/// Values are kept as strings until we know the field of the scenario.
+#[derive(Debug, Clone, Default)]
+pub struct RunOptions {
+    pub k: Option<u32>,
+    /// JSON file replacing the circuit and the public inputs, see `inputs`.
+    pub input: Option<PathBuf>,
+    /// Replaces the public inputs of the scenario, one `Vec` per instance column.
+    /// Takes precedence over the instances of `input`.
+    pub public_inputs: Option<Vec<Vec<String>>>,
+    /// Applied after the edits of the scenario itself.
+    pub cells: Vec<(CellTarget, String)>,
+    /// Prove with the real backend instead of `MockProver`.
+    pub real: Option<ParamsCache>,
+}
+
+/// Parses a decimal or `0x` hex field element, with an optional leading `-`.
+///
+/// Numbers larger than the modulus are reduced, which is exactly what
+/// the field overflow attacks need.
+pub fn parse_field<F: PrimeField>(s: &str) -> Result<F, String> {
+    let invalid = || format!("invalid field element `{}`", s);
+
+    let trimmed = s.trim();
+    let (negative, unsigned) = match trimmed.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, trimmed),
+    };
+    let (radix, digits) = match unsigned.strip_prefix("0x") {
+        Some(digits) => (16, digits),
+        None => (10, unsigned),
+    };
+    if digits.is_empty() {
+        return Err(invalid());
+    }
+
+    let mut value = F::ZERO;
+    for c in digits.chars() {
+        let digit = c.to_digit(radix).ok_or_else(invalid)?;
+        value = value * F::from(radix as u64) + F::from(digit as u64);
+    }
+    Ok(if negative { -value } else { value })
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::halo2curves::{bn256::Fr, ff::Field};
+
+    use super::*;
+
+    /// The order of bn256 `Fr`.
+    const MODULUS: &str =
+        "21888242871839275222246405745257275088548364400416034343698204186575808495617";
+
+    fn fr(s: &str) -> Fr {
+        parse_field(s).unwrap()
+    }
+
+    #[test]
+    fn decimal_and_hex() {
+        assert_eq!(fr("12"), Fr::from(12));
+        assert_eq!(fr(" 12 "), Fr::from(12));
+        assert_eq!(fr("0x0c"), Fr::from(12));
+        assert_eq!(fr("0xFF"), Fr::from(255));
+    }
+
+    #[test]
+    fn negative() {
+        assert_eq!(fr("-1"), -Fr::ONE);
+        assert_eq!(fr("-0x10"), -Fr::from(16));
+        assert_eq!(fr("-0"), Fr::ZERO);
+    }
+
+    #[test]
+    fn out_of_range_wraps() {
+        assert_eq!(fr(MODULUS), Fr::ZERO);
+        assert_eq!(fr(&MODULUS.replace("617", "618")), Fr::ONE);
+        assert_eq!(fr(&format!("-{}", MODULUS)), Fr::ZERO);
+    }
+
+    #[test]
+    fn invalid() {
+        for s in ["", "-", "0x", "12a", "0xg", "1.5", "--1"] {
+            assert!(parse_field::<Fr>(s).is_err(), "{:?} parsed", s);
+        }
+    }
+}
@@ -0,0 +1,115 @@
+use halo2_proofs::dev::VerifyFailure;
+use std::fmt;
+
+use super::Verdict;
+
+/// What happened when we ran a scenario through `MockProver` or the real prover.
+#[derive(Debug)]
+pub struct ScenarioResult {
+    pub name: &'static str,
+    pub expected: Verdict,
+    pub observed: Verdict,
+    pub failures: Vec<VerifyFailure>,
+    /// `MockProver::run` or keygen itself failed (e.g. `k` is too small) or the tampering
+    /// didn't find its cells, so the circuit never got to the verifier.
+    pub error: Option<String>,
+}
+
+impl ScenarioResult {
+    pub fn verified(
+        name: &'static str,
+        expected: Verdict,
+        result: Result<(), Vec<VerifyFailure>>,
+    ) -> Self {
+        let observed = Verdict::of(&result);
+        Self {
+            name,
+            expected,
+            observed,
+            failures: result.err().unwrap_or_default(),
+            error: None,
+        }
+    }
+
+    /// A real proof went through `verify_proof`, which doesn't say which constraint failed.
+    pub fn proved(name: &'static str, expected: Verdict, observed: Verdict) -> Self {
+        Self {
+            name,
+            expected,
+            observed,
+            failures: vec![],
+            error: None,
+        }
+    }
+
+    pub fn errored(name: &'static str, expected: Verdict, error: impl fmt::Display) -> Self {
+        Self {
+            name,
+            expected,
+            observed: Verdict::Reject,
+            failures: vec![],
+            error: Some(error.to_string()),
+        }
+    }
+
+    /// The verifier said what we expected it to say.
+    pub fn is_expected(&self) -> bool {
+        self.error.is_none() && self.observed == self.expected
+    }
+}
+
+impl fmt::Display for ScenarioResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let status = if self.is_expected() { "ok" } else { "FAILED" };
+        write!(
+            f,
+            "{:<6} {} (expected {:?}, got {:?})",
+            status, self.name, self.expected, self.observed
+        )?;
+        if let Some(error) = &self.error {
+            write!(f, "\n         error: {}", error)?;
+        }
+        for failure in self.failures.iter() {
+            write!(f, "\n         {}", failure)?;
+        }
+        Ok(())
+    }
+}
+
+/// All the results of a run, collected before anything gets reported.
+#[derive(Debug, Default)]
+pub struct Report {
+    pub results: Vec<ScenarioResult>,
+}
+
+impl Report {
+    pub fn unexpected(&self) -> impl Iterator<Item = &ScenarioResult> {
+        self.results.iter().filter(|r| !r.is_expected())
+    }
+
+    pub fn is_success(&self) -> bool {
+        self.unexpected().next().is_none()
+    }
+}
+
+impl FromIterator<ScenarioResult> for Report {
+    fn from_iter<I: IntoIterator<Item = ScenarioResult>>(iter: I) -> Self {
+        Self {
+            results: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for result in self.results.iter() {
+            writeln!(f, "{}", result)?;
+        }
+        write!(
+            f,
+            "{} scenarios, {} unexpected",
+            self.results.len(),
+            self.unexpected().count()
+        )
+    }
+}
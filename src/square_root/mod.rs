@@ -1,9 +1,15 @@
-use halo2_proofs::{circuit::Value, dev::MockProver, halo2curves::bn256::Fr as Fp};
+use halo2_proofs::{circuit::Value, halo2curves::bn256::Fr as Fp};
 
-mod sroot0;
-mod sroot1;
+use crate::{
+    chips::CanonicalRule,
+    scenario::{CircuitScenario, Registry, Verdict},
+};
 
-pub fn square_root() {
+pub mod sroot0;
+pub mod sroot1;
+pub mod sroot2;
+
+pub fn register(registry: &mut Registry) {
     let n = Fp::from(9);
     let root = Fp::from(3);
 
@@ -13,8 +19,13 @@ pub fn square_root() {
     let circuit = sroot0::SquareRootCircuit {
         root: Value::known(fake_root),
     };
-    let prover = MockProver::run(3, &circuit, vec![vec![n]]).unwrap();
-    assert!(prover.verify().is_ok());
+    registry.register(CircuitScenario::new(
+        "sroot0/negative-root",
+        circuit,
+        3,
+        vec![vec![n]],
+        Verdict::Accept,
+    ));
 
     // ======================================================
     // if we try to pass a fake root, the proof will fail
@@ -22,12 +33,80 @@ pub fn square_root() {
     let circuit = sroot1::SquareRootCircuit {
         root: Value::known(fake_root),
     };
-    let prover = MockProver::run(5, &circuit, vec![vec![n]]).unwrap();
-    assert!(prover.verify().is_err());
+    registry.register(CircuitScenario::new(
+        "sroot1/negative-root",
+        circuit,
+        5,
+        vec![vec![n]],
+        Verdict::Reject,
+    ));
 
     let circuit = sroot1::SquareRootCircuit {
         root: Value::known(root),
     };
-    let prover = MockProver::run(5, &circuit, vec![vec![n]]).unwrap();
-    assert!(prover.verify().is_ok());
+    registry.register(CircuitScenario::new(
+        "sroot1/honest",
+        circuit,
+        5,
+        vec![vec![n]],
+        Verdict::Accept,
+    ));
+
+    // ======================================================
+    // sroot2 range checks the root with its bit decomposition instead of a tiny table,
+    // and the caller picks which root is the canonical one.
+    // one row per bit of the field, so we need k = 9
+    let sroot2 = |root: Fp, rule: CanonicalRule| sroot2::SquareRootCircuit {
+        root: Value::known(root),
+        rule,
+    };
+
+    // 3 <= (p - 1) / 2 < -3
+    registry.register(CircuitScenario::new(
+        "sroot2/smaller",
+        sroot2(root, CanonicalRule::Smaller),
+        9,
+        vec![vec![n]],
+        Verdict::Accept,
+    ));
+    registry.register(CircuitScenario::new(
+        "sroot2/smaller-negative-root",
+        sroot2(fake_root, CanonicalRule::Smaller),
+        9,
+        vec![vec![n]],
+        Verdict::Reject,
+    ));
+
+    // p is odd, so -3 == p - 3 is even: this time -3 is the canonical root
+    registry.register(CircuitScenario::new(
+        "sroot2/even-lsb",
+        sroot2(fake_root, CanonicalRule::EvenLsb),
+        9,
+        vec![vec![n]],
+        Verdict::Accept,
+    ));
+    registry.register(CircuitScenario::new(
+        "sroot2/even-lsb-odd-root",
+        sroot2(root, CanonicalRule::EvenLsb),
+        9,
+        vec![vec![n]],
+        Verdict::Reject,
+    ));
+
+    // a u64 root, way out of sroot1's table
+    let big_root = Fp::from(u64::MAX);
+    registry.register(CircuitScenario::new(
+        "sroot2/u64",
+        sroot2(big_root, CanonicalRule::BitLength(64)),
+        9,
+        vec![vec![big_root * big_root]],
+        Verdict::Accept,
+    ));
+    registry.register(CircuitScenario::new(
+        "sroot2/u64-negative-root",
+        sroot2(-big_root, CanonicalRule::BitLength(64)),
+        9,
+        vec![vec![big_root * big_root]],
+        Verdict::Reject,
+    ));
 }
@@ -4,9 +4,11 @@ use halo2_proofs::{
     plonk::{Advice, Circuit, Column, ConstraintSystem, ErrorFront, Instance, Selector},
     poly::Rotation,
 };
+use serde::Deserialize;
 
-#[derive(Debug, Default, Clone, Copy)]
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
 pub struct SquareRootCircuit {
+    #[serde(default, deserialize_with = "crate::inputs::value")]
     pub root: Value<Fp>,
 }
 
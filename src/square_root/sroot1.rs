@@ -6,9 +6,11 @@ use halo2_proofs::{
     },
     poly::Rotation,
 };
+use serde::Deserialize;
 
-#[derive(Debug, Default, Clone, Copy)]
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
 pub struct SquareRootCircuit {
+    #[serde(default, deserialize_with = "crate::inputs::value")]
     pub root: Value<Fp>,
 }
 
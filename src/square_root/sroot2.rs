@@ -0,0 +1,63 @@
+use halo2_proofs::{
+    circuit::{Layouter, SimpleFloorPlanner, Value},
+    halo2curves::ff::PrimeField,
+    plonk::{Circuit, Column, ConstraintSystem, ErrorFront, Instance},
+};
+use serde::Deserialize;
+
+use crate::chips::{CanonicalRule, SquareRootChip, SquareRootConfig};
+
+/// `sroot1` without the `0..=10` table: the root is range checked by its bit decomposition,
+/// and only the canonical root under `rule` is accepted.
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+pub struct SquareRootCircuit<F: PrimeField> {
+    #[serde(default, deserialize_with = "crate::inputs::value")]
+    pub root: Value<F>,
+    /// Part of the circuit, not of the witness: it changes the fixed columns and the selectors.
+    #[serde(default)]
+    pub rule: CanonicalRule,
+}
+
+#[derive(Clone, Debug)]
+pub struct SquareRootCircuitConfig {
+    square_root: SquareRootConfig,
+    instance: Column<Instance>,
+}
+
+impl<F: PrimeField> Circuit<F> for SquareRootCircuit<F> {
+    type Config = SquareRootCircuitConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self {
+            root: Value::unknown(),
+            rule: self.rule,
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        let advice = [(); 4].map(|_| meta.advice_column());
+        let bound = meta.fixed_column();
+        let constants = meta.fixed_column();
+        meta.enable_constant(constants);
+
+        let instance = meta.instance_column();
+        meta.enable_equality(instance);
+
+        SquareRootCircuitConfig {
+            square_root: SquareRootChip::configure(meta, advice, bound),
+            instance,
+        }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), ErrorFront> {
+        let chip = SquareRootChip::construct(config.square_root, self.rule);
+        let square = chip.assign(layouter.namespace(|| "square root"), self.root)?;
+        layouter.constrain_instance(square.cell(), config.instance, 0)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,161 @@
+use halo2_proofs::{
+    arithmetic::Field,
+    dev::{CellValue, MockProver},
+    halo2curves::ff::FromUniformBytes,
+    plonk::{Circuit, ErrorFront},
+};
+use std::{fmt, str::FromStr};
+
+use crate::layout::Layout;
+
+/// Names an advice cell the way the circuit does: region name and annotation.
+/// When a region is assigned several times (e.g. "merkle prove" once per layer),
+/// `occurrence` picks which one, in assignment order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CellTarget {
+    pub region: String,
+    pub annotation: String,
+    pub occurrence: usize,
+}
+
+impl CellTarget {
+    pub fn new(region: &str, annotation: &str) -> Self {
+        Self {
+            region: region.to_string(),
+            annotation: annotation.to_string(),
+            occurrence: 0,
+        }
+    }
+
+    pub fn nth(mut self, occurrence: usize) -> Self {
+        self.occurrence = occurrence;
+        self
+    }
+}
+
+impl fmt::Display for CellTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.region, self.annotation)?;
+        if self.occurrence > 0 {
+            write!(f, "#{}", self.occurrence)?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses `region/annotation` or `region/annotation#occurrence`,
+/// e.g. `main region/out` or `merkle prove/result#1`.
+impl FromStr for CellTarget {
+    type Err = TamperError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (region, annotation) = s
+            .split_once('/')
+            .ok_or_else(|| TamperError::InvalidTarget(s.to_string()))?;
+        let (annotation, occurrence) = match annotation.rsplit_once('#') {
+            Some((annotation, n)) => (
+                annotation,
+                n.parse()
+                    .map_err(|_| TamperError::InvalidTarget(s.to_string()))?,
+            ),
+            None => (annotation, 0),
+        };
+        Ok(Self::new(region, annotation).nth(occurrence))
+    }
+}
+
+#[derive(Debug)]
+pub enum TamperError {
+    InvalidTarget(String),
+    UnknownCell(CellTarget),
+    Synthesis(ErrorFront),
+}
+
+impl fmt::Display for TamperError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TamperError::InvalidTarget(s) => {
+                write!(f, "invalid cell `{}`, expected region/annotation", s)
+            }
+            TamperError::UnknownCell(target) => write!(f, "no advice cell named `{}`", target),
+            TamperError::Synthesis(err) => write!(f, "synthesis failed: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for TamperError {}
+
+impl From<ErrorFront> for TamperError {
+    fn from(err: ErrorFront) -> Self {
+        TamperError::Synthesis(err)
+    }
+}
+
+/// A value to write in an absolute (column, row) advice cell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Override<F> {
+    pub column: usize,
+    pub row: usize,
+    pub value: F,
+}
+
+/// A list of witness edits addressed by name instead of by index,
+/// so the attacks keep working when the layout of a circuit moves.
+#[derive(Debug, Clone)]
+pub struct Tamper<F> {
+    edits: Vec<(CellTarget, F)>,
+}
+
+impl<F> Default for Tamper<F> {
+    fn default() -> Self {
+        Self { edits: vec![] }
+    }
+}
+
+impl<F: Field> Tamper<F> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set(mut self, target: CellTarget, value: F) -> Self {
+        self.edits.push((target, value));
+        self
+    }
+
+    pub fn edits(&self) -> &[(CellTarget, F)] {
+        &self.edits
+    }
+
+    /// Finds the absolute position of every edit in the layout of the circuit.
+    pub fn resolve(&self, layout: &Layout<F>) -> Result<Vec<Override<F>>, TamperError> {
+        self.edits
+            .iter()
+            .map(|(target, value)| {
+                let cell = layout
+                    .find(&target.region, &target.annotation)
+                    .nth(target.occurrence)
+                    .ok_or_else(|| TamperError::UnknownCell(target.clone()))?;
+                Ok(Override {
+                    column: cell.column.index(),
+                    row: cell.row,
+                    value: *value,
+                })
+            })
+            .collect()
+    }
+}
+
+impl<F: FromUniformBytes<64> + Ord> Tamper<F> {
+    /// Overwrites the witness of `prover`, which must have been run on `circuit`.
+    pub fn apply<C: Circuit<F>>(
+        &self,
+        circuit: &C,
+        prover: &mut MockProver<F>,
+    ) -> Result<(), TamperError> {
+        let layout = Layout::synthesize(circuit)?;
+        for o in self.resolve(&layout)? {
+            prover.advice_mut(o.column)[o.row] = CellValue::Assigned(o.value);
+        }
+        Ok(())
+    }
+}